@@ -8,6 +8,7 @@ pub mod proto;
 
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::path::Path;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
@@ -19,6 +20,9 @@ use crypto::sha1::Sha1;
 use log::info;
 use rusqlite::params;
 use rusqlite::Connection;
+use rusqlite::ErrorCode;
+use rusqlite::Transaction;
+use rusqlite::TransactionBehavior;
 use rusqlite::NO_PARAMS;
 
 use self::anki_card::AnkiCard;
@@ -32,12 +36,16 @@ use self::anki_note::AnkiNote;
 use crate::anki::anki_deck::get_anki_decks_from_table;
 use crate::named::get_by_name;
 
+/// The separator between the field values in the flds column of the notes table.
+pub static ANKI_FIELD_SEPARATOR: &str = "\x1f";
+
 #[derive(Debug)]
 pub struct AnkiNoteInfo {
     pub id: i64,
     pub fields: HashMap<String, String>,
     pub model_name: String,
     pub deck_name: String,
+    pub tags: Vec<String>,
 }
 
 pub struct AnkiBlock {
@@ -60,6 +68,36 @@ pub fn close_connection(connection: Connection) -> rusqlite::Result<()> {
     }
 }
 
+/// Start a transaction which locks the collection for other processes until it is committed.
+/// Anki keeps the collection locked while it is open, so this fails if anki is running.
+pub fn begin_exclusive_transaction(connection: &mut Connection) -> Result<Transaction<'_>> {
+    connection
+        .transaction_with_behavior(TransactionBehavior::Exclusive)
+        .map_err(get_lock_error)
+}
+
+/// Write a consistent copy of the collection to the given path, which must not exist yet.
+pub fn backup_collection(connection: &Connection, backup_path: &Path) -> Result<()> {
+    info!("Backing up collection to {:?}", backup_path);
+    connection
+        .execute("VACUUM INTO ?1", params![backup_path.to_str().unwrap()])
+        .map_err(get_lock_error)
+        .context(format!("While backing up collection to {:?}", backup_path))?;
+    Ok(())
+}
+
+fn get_lock_error(err: rusqlite::Error) -> anyhow::Error {
+    match err {
+        rusqlite::Error::SqliteFailure(ref failure, _)
+            if failure.code == ErrorCode::DatabaseBusy
+                || failure.code == ErrorCode::DatabaseLocked =>
+        {
+            anyhow!("The anki collection is locked. Close anki before synchronizing.")
+        }
+        _ => anyhow::Error::from(err),
+    }
+}
+
 pub fn get_csum(input: &str) -> i64 {
     let mut hasher = Sha1::new();
     hasher.input_str(input);
@@ -181,6 +219,57 @@ pub fn sort_fields_by_occurence(
         .collect()
 }
 
+/// The tags in the format of the tags column of the notes table: Sorted, separated by spaces and
+/// with a space at the beginning and end. Whitespace within a tag is replaced by underscores.
+pub fn format_anki_tags<T: AsRef<str>>(tags: &[T]) -> String {
+    let mut tags: Vec<String> = tags
+        .iter()
+        .map(|tag| {
+            tag.as_ref()
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join("_")
+        })
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    match tags.is_empty() {
+        true => "".to_string(),
+        false => format!(" {} ", tags.join(" ")),
+    }
+}
+
+/// A copy of the note with new values for all its fields.
+pub fn get_anki_note_with_field_values(
+    collection: &AnkiCollection,
+    anki_note: &AnkiNote,
+    values: &[String],
+) -> Result<AnkiNote> {
+    let model = collection
+        .models
+        .iter()
+        .find(|model| model.id == anki_note.mid)
+        .ok_or_else(|| anyhow!("No model with id {} in collection", anki_note.mid))?;
+    let sfld = values
+        .get(model.sortf as usize)
+        .ok_or_else(|| anyhow!("Sort field entry in anki model is invalid for this model?"))?
+        .clone();
+    Ok(AnkiNote {
+        id: anki_note.id,
+        guid: anki_note.guid.clone(),
+        mid: anki_note.mid,
+        mod_: get_unix_time(),
+        usn: -1, // Force pushing to server
+        tags: anki_note.tags.clone(),
+        flds: values.join(ANKI_FIELD_SEPARATOR),
+        sfld,
+        csum: get_csum(&values[0]),
+        flags: anki_note.flags,
+        data: anki_note.data.clone(),
+    })
+}
+
 pub fn get_new_anki_note(note_info: &AnkiNoteInfo, model: &AnkiModel) -> Result<AnkiNote> {
     let unix_time = get_unix_time();
     let guid = note_info.id;
@@ -190,8 +279,7 @@ pub fn get_new_anki_note(note_info: &AnkiNoteInfo, model: &AnkiModel) -> Result<
         "Looking up field entries for note {}",
         note_info.id
     ))?;
-    let separator = "";
-    let joined_fields = sorted_field_entries.join(separator);
+    let joined_fields = sorted_field_entries.join(ANKI_FIELD_SEPARATOR);
     let csum = get_csum(&sorted_field_entries[0].clone());
     let sort_field_name: String = model
         .flds
@@ -206,7 +294,7 @@ pub fn get_new_anki_note(note_info: &AnkiNoteInfo, model: &AnkiModel) -> Result<
         mid: model.id,
         mod_: unix_time,
         usn: -1, // Force pushing to server
-        tags: "".to_string(),
+        tags: format_anki_tags(&note_info.tags),
         flds: joined_fields,
         sfld: sfld_contents,
         csum,
@@ -268,6 +356,69 @@ pub fn update_anki_note_contents(
     )
 }
 
+pub fn read_anki_note_tags(connection: &Connection, note_id: i64) -> rusqlite::Result<String> {
+    connection.query_row(
+        "SELECT tags FROM notes WHERE id = (?1)",
+        params![note_id],
+        |row| row.get(0),
+    )
+}
+
+pub fn update_anki_note_tags(
+    connection: &Connection,
+    note_id: i64,
+    tags: &str,
+    mod_: i64,
+) -> rusqlite::Result<usize> {
+    info!("Updating tags of note {}", note_id);
+    connection.execute(
+        "UPDATE notes SET (mod, usn, tags) = (?1, -1, ?2) WHERE id = (?3)",
+        params![mod_, tags, note_id],
+    )
+}
+
+/// Delete the note and its cards and add them to the graves so that the deletion is synchronized.
+pub fn delete_anki_note(connection: &Connection, note_id: i64) -> Result<()> {
+    info!("Deleting note {}", note_id);
+    let mut stmt = connection.prepare("SELECT id FROM cards WHERE nid = (?1)")?;
+    let card_ids = stmt
+        .query_map(params![note_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+    for card_id in card_ids {
+        connection.execute(
+            "INSERT INTO graves (usn, oid, type) VALUES (-1, ?1, 0)",
+            params![card_id],
+        )?;
+    }
+    connection.execute("DELETE FROM cards WHERE nid = (?1)", params![note_id])?;
+    connection.execute(
+        "INSERT INTO graves (usn, oid, type) VALUES (-1, ?1, 1)",
+        params![note_id],
+    )?;
+    connection.execute("DELETE FROM notes WHERE id = (?1)", params![note_id])?;
+    Ok(())
+}
+
+pub fn suspend_anki_cards(connection: &Connection, note_id: i64) -> rusqlite::Result<usize> {
+    info!("Suspending cards of note {}", note_id);
+    connection.execute(
+        "UPDATE cards SET (queue, mod, usn) = (-1, ?1, -1) WHERE nid = (?2) AND queue != -1",
+        params![get_unix_time(), note_id],
+    )
+}
+
+pub fn move_anki_cards_to_deck(
+    connection: &Connection,
+    note_id: i64,
+    deck: &AnkiDeck,
+) -> rusqlite::Result<usize> {
+    info!("Moving cards of note {} to deck {}", note_id, deck.name);
+    connection.execute(
+        "UPDATE cards SET (did, mod, usn) = (?1, ?2, -1) WHERE nid = (?3) AND did != (?1)",
+        params![deck.id, get_unix_time(), note_id],
+    )
+}
+
 pub fn read_notes(connection: &Connection) -> Result<Vec<AnkiNote>> {
     let mut stmt = connection.prepare(
         "SELECT id, guid, mid, mod, usn, tags, flds, sfld, csum, flags, data FROM notes",
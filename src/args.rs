@@ -3,7 +3,9 @@ use std::str::FromStr;
 
 use clap::Parser;
 
+use crate::config_opts::ConfigOpts;
 use crate::filter_options::FilterOptions;
+use crate::graph::Direction;
 use crate::journal_opts::JournalOpts;
 use crate::paper_opts::PaperOpts;
 
@@ -15,7 +17,9 @@ pub struct Opts {
     pub subcmd: SubCommand,
     /// The note folder to run on
     pub folder: Option<PathBuf>,
-    /// The path to the database in which to store the notes and their links for fast access times
+    /// The path to the database in which to store the notes and their links for fast access times.
+    /// Databases ending in .sqlite, .sqlite3 or .db are stored in SQLite, all others in YAML.
+    /// SQLite databases only parse the files which changed since the last run again.
     pub database: Option<PathBuf>,
     /// Run only on the top level folder.
     #[clap(short, long)]
@@ -24,6 +28,21 @@ pub struct Opts {
     /// from terminal output in emacs (via term-char-mode)
     #[clap(short, long)]
     pub add_identifier: bool,
+    /// The profile from the settings whose note folder, database and other paths are used
+    /// for all paths which are not given explicitly
+    #[clap(long)]
+    pub profile: Option<String>,
+    /// The settings file to use instead of the one in the config directory.
+    /// Can also be set with the PUNDIT_CONFIG environment variable.
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+    /// Exit with an error if the settings file cannot be read or is invalid, instead of using the default settings
+    #[clap(long)]
+    pub strict_config: bool,
+    /// The folder containing the templates for new notes. Defaults to the templates folder in the
+    /// pundit settings directory.
+    #[clap(long)]
+    pub template_folder: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
@@ -41,8 +60,18 @@ pub enum SubCommand {
     PankitGetNote(PankitGetNote),
     ListGraph(ListGraph),
     Graph(FindGraph),
+    Path(ShortestPath),
+    ExportGraph(ExportGraph),
+    Stats(ShowStats),
+    Check(CheckNotes),
+    FixLinks(FixLinks),
+    SuggestLinks(SuggestLinks),
+    Search(SearchNotes),
+    ListTags(ListTags),
+    Tag(EditTags),
     Journal(JournalOpts),
     Paper(PaperOpts),
+    Config(ConfigOpts),
 }
 
 /// List notes.
@@ -52,6 +81,26 @@ pub struct ListNotes {
     pub filter: Option<FilterOptions>,
 }
 
+/// List all tags used in the notes.
+#[derive(Parser, Debug)]
+pub struct ListTags {
+    #[clap(subcommand)]
+    pub filter: Option<FilterOptions>,
+}
+
+/// Add or remove tags of a note.
+#[derive(Parser, Debug)]
+pub struct EditTags {
+    /// The path of the note
+    pub filename: PathBuf,
+    /// A tag to add to the note (can be given multiple times)
+    #[clap(short, long)]
+    pub add: Vec<String>,
+    /// A tag to remove from the note (can be given multiple times)
+    #[clap(short, long)]
+    pub remove: Vec<String>,
+}
+
 /// Interactively choose note 2 and then display a (relative) link from note 1 to note 2.
 #[derive(Parser, Debug)]
 pub struct GetLinkTextInteractively {
@@ -91,6 +140,9 @@ pub struct FindBacklinks {
 pub struct FindNoteInteractively {
     #[clap(subcommand)]
     pub filter: Option<FilterOptions>,
+    /// Sort the notes by the length of the shortest link path to this note
+    #[clap(long)]
+    pub distance_from: Option<PathBuf>,
 }
 
 /// Create a new note with a given title (first ensure that it does not exist already).
@@ -98,21 +150,69 @@ pub struct FindNoteInteractively {
 pub struct NewNote {
     /// Optional: Only list notes which contain this string in the title
     pub title: String,
+    /// The name of the template to fill in below the title. By default, the template for the note folder is used, if there is one.
+    #[clap(long)]
+    pub template: Option<String>,
 }
 
-/// Delete a note. This will only delete the note if no other notes link to it. Otherwise it will print a list of notes linking to this note.
+/// Delete a note. By default, this will only delete the note if no other notes link to it. Otherwise it will print a list of notes linking to this note.
 #[derive(Parser, Debug)]
 pub struct DeleteNote {
     /// The path to the note which to delete
     pub filename: PathBuf,
+    /// How to deal with links to the note: refuse to delete it, turn the links into plain text or point them to the replacement note
+    #[clap(long, possible_values = &["refuse", "unlink", "replace"], default_value = "refuse")]
+    pub backlinks: BacklinkHandling,
+    /// The note to point the links to when using --backlinks replace
+    #[clap(long)]
+    pub replacement: Option<PathBuf>,
+    /// Move the note to the trash folder in the data directory instead of deleting it
+    #[clap(long)]
+    pub trash: bool,
+}
+
+#[derive(Parser, Debug)]
+pub enum BacklinkHandling {
+    /// Do not delete the note if other notes link to it
+    Refuse,
+    /// Replace the links by their description
+    Unlink,
+    /// Point the links to a replacement note
+    Replace,
+}
+
+impl FromStr for BacklinkHandling {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "refuse" => Ok(BacklinkHandling::Refuse),
+            "unlink" => Ok(BacklinkHandling::Unlink),
+            "replace" => Ok(BacklinkHandling::Replace),
+            _ => Err("no match"),
+        }
+    }
 }
 
-/// Rename a note
+/// Rename a note. This changes the title, moves the file to the corresponding filename and updates the links in all notes linking to it.
 #[derive(Parser, Debug)]
 pub struct RenameNote {
     /// The path to the note which to rename
     pub filename: PathBuf,
+    /// The new title of the note
     pub new_name: String,
+    /// Only print the planned changes without modifying any files
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+/// Find mentions of the titles of other notes (ignoring case) which are not linked yet
+/// and print them together with their line numbers and the link that would be inserted.
+#[derive(Parser, Debug)]
+pub struct SuggestLinks {
+    pub filename: PathBuf,
+    /// Replace the first mention of every note by a link to it
+    #[clap(long)]
+    pub apply: bool,
 }
 
 /// Select a note interactively from the graph component for a specific note
@@ -120,6 +220,75 @@ pub struct RenameNote {
 pub struct FindGraph {
     /// The path of the note
     pub filename: PathBuf,
+    /// Sort the notes by the length of the shortest link path to the note instead of by title
+    #[clap(long)]
+    pub sort_by_distance: bool,
+    #[clap(flatten)]
+    pub neighbourhood: NeighbourhoodOptions,
+}
+
+/// Show the titles of the notes along the shortest link path between two notes
+#[derive(Parser, Debug)]
+pub struct ShortestPath {
+    /// The note at which the path starts
+    pub note1: PathBuf,
+    /// The note at which the path ends
+    pub note2: PathBuf,
+    /// Follow links in both directions instead of only from the linking note to the linked note
+    #[clap(short, long)]
+    pub undirected: bool,
+}
+
+/// Search the contents of all notes and print the filename, line number and contents of every matching line.
+/// When a database is used, word searches use an index stored next to it.
+#[derive(Parser, Debug)]
+pub struct SearchNotes {
+    /// The words to search for. Notes match if they contain all of the words (ignoring case).
+    #[clap(required = true)]
+    pub query: Vec<String>,
+    /// Interpret the query as a regular expression which is matched against every line
+    #[clap(long)]
+    pub regex: bool,
+    /// Select one of the matching lines via fzf and print the filename of the note
+    #[clap(short, long)]
+    pub interactive: bool,
+}
+
+/// Report broken links, links of notes to themselves, duplicate and missing titles and orphaned notes.
+/// Unlike the other commands, this does not abort on the first problem.
+#[derive(Parser, Debug)]
+pub struct CheckNotes {
+    /// Print one line per problem or a JSON list of all problems (for editor integrations)
+    #[clap(long, possible_values = &["text", "json"], default_value = "text")]
+    pub output: OutputFormat,
+}
+
+/// Repair links to notes that were moved or renamed outside of pundit. Each broken link is pointed to the note
+/// with the title given in the link description or, if there is none, to the note with the same date in its filename.
+#[derive(Parser, Debug)]
+pub struct FixLinks {
+    /// Only print the planned changes without modifying any files
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+pub enum OutputFormat {
+    /// Human readable text
+    Text,
+    /// JSON
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err("no match"),
+        }
+    }
 }
 
 /// List all notes in the graph component for a specific note
@@ -127,20 +296,178 @@ pub struct FindGraph {
 pub struct ListGraph {
     /// The path of the note
     pub filename: PathBuf,
+    #[clap(flatten)]
+    pub neighbourhood: NeighbourhoodOptions,
+}
+
+/// Which notes around a note to include in its graph
+#[derive(Parser, Debug)]
+pub struct NeighbourhoodOptions {
+    /// Only include notes which are at most this many links away from the note
+    #[clap(long)]
+    pub depth: Option<usize>,
+    /// Which links to follow: those from a note to other notes, those from other notes to it or both
+    #[clap(long, possible_values = &["out", "in", "both"], default_value = "both")]
+    pub direction: Direction,
+    /// Leave out notes that many notes link to (such as the journal base note or the paper note),
+    /// so that the graph is not connected through them
+    #[clap(long)]
+    pub exclude_hubs: bool,
+    /// The number of notes which need to link to a note for it to count as a hub
+    #[clap(long, default_value = "20")]
+    pub hub_min_backlinks: usize,
+}
+
+/// Write the graph of links between all notes (or the graph component of a single note) as Graphviz DOT,
+/// GraphML or a JSON list of nodes and edges. The nodes contain the title, path, folder and tags of the notes.
+#[derive(Parser, Debug)]
+pub struct ExportGraph {
+    /// Only export the graph component of this note
+    #[clap(long)]
+    pub note: Option<PathBuf>,
+    /// The format to write the graph in
+    #[clap(long, possible_values = &["dot", "graphml", "json"], default_value = "dot")]
+    pub format: GraphFormat,
+    /// The file to write the graph to. If not given, the graph is printed.
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Show statistics of the graph of links between the notes: The number of notes and links, the most linked-to notes,
+/// orphans without any links, dead ends without outgoing links, articulation points whose removal would disconnect
+/// the graph and the connected components.
+#[derive(Parser, Debug)]
+pub struct ShowStats {
+    /// Print the statistics as text or as JSON
+    #[clap(long, possible_values = &["text", "json"], default_value = "text")]
+    pub output: OutputFormat,
+    /// The number of most linked-to notes to show
+    #[clap(long, default_value = "10")]
+    pub num_hubs: usize,
+}
+
+#[derive(Parser, Debug)]
+pub enum GraphFormat {
+    /// Graphviz DOT
+    Dot,
+    /// GraphML
+    Graphml,
+    /// JSON list of nodes and edges
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "graphml" => Ok(GraphFormat::Graphml),
+            "json" => Ok(GraphFormat::Json),
+            _ => Err("no match"),
+        }
+    }
 }
 
 /// Update the anki contents from the notes.
 #[derive(Parser, Debug)]
 pub struct Pankit {
-    /// The path of the anki database to update
-    pub database: PathBuf,
-    /// The path of the pankit database which is used for synchronization of the pundit notes and anki database
-    pub pankit_db: PathBuf,
+    /// The path of the anki database to update. Can be left out if the profile contains it.
+    pub database: Option<PathBuf>,
+    /// The path of the pankit database which is used for synchronization of the pundit notes and anki database.
+    /// Can be left out if the profile contains it.
+    pub pankit_db: Option<PathBuf>,
     /// How to deal with conflicting contents between anki and pundit that cannot be resolved automatically
     // #[clap(subcommand)]
     #[clap(default_value = "error")]
-    #[clap(possible_values = &["ignore", "error", "pundit", "anki"], default_value = "error")]
+    #[clap(possible_values = &["ignore", "error", "pundit", "anki", "interactive"], default_value = "error")]
     pub conflict_handling: ConflictHandling,
+    /// What to do with anki notes whose entry was removed from the pundit notes
+    #[clap(long, possible_values = &["keep", "delete", "suspend", "orphan"], default_value = "keep")]
+    pub removed_in_pundit: PunditRemovalHandling,
+    /// What to do with pundit entries whose anki note was deleted in anki
+    #[clap(long, possible_values = &["keep", "re-add", "remove"], default_value = "keep")]
+    pub deleted_in_anki: AnkiDeletionHandling,
+    /// Derive additional anki tags from the pundit note containing the anki note (can be given multiple times)
+    #[clap(long, possible_values = &["title", "folder", "filetags"])]
+    pub derive_tags: Vec<TagSource>,
+    /// Save a timestamped copy of the anki collection next to it before synchronizing
+    #[clap(long)]
+    pub backup: bool,
+    /// Only print a summary of the changes without modifying the anki database, the pankit database or the notes
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Print the summary of a dry run as text or as JSON
+    #[clap(long, possible_values = &["text", "json"], default_value = "text")]
+    pub output: OutputFormat,
+}
+
+#[derive(Parser, Debug, Clone, Copy)]
+pub enum PunditRemovalHandling {
+    /// Leave the anki note as it is
+    Keep,
+    /// Delete the anki note and its cards
+    Delete,
+    /// Suspend the cards of the anki note
+    Suspend,
+    /// Move the cards of the anki note to the deck named ORPHANED_DECK_NAME
+    Orphan,
+}
+
+impl FromStr for PunditRemovalHandling {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(PunditRemovalHandling::Keep),
+            "delete" => Ok(PunditRemovalHandling::Delete),
+            "suspend" => Ok(PunditRemovalHandling::Suspend),
+            "orphan" => Ok(PunditRemovalHandling::Orphan),
+            _ => Err("no match"),
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone, Copy)]
+pub enum AnkiDeletionHandling {
+    /// Leave the pundit entry as it is without adding the note to anki again
+    Keep,
+    /// Add the note to anki again
+    ReAdd,
+    /// Remove the entry from the pundit note
+    Remove,
+}
+
+impl FromStr for AnkiDeletionHandling {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(AnkiDeletionHandling::Keep),
+            "re-add" => Ok(AnkiDeletionHandling::ReAdd),
+            "remove" => Ok(AnkiDeletionHandling::Remove),
+            _ => Err("no match"),
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone, Copy)]
+pub enum TagSource {
+    /// The title of the pundit note
+    Title,
+    /// The folder of the pundit note relative to the note folder, as a hierarchical anki tag
+    Folder,
+    /// The tags of the pundit note
+    Filetags,
+}
+
+impl FromStr for TagSource {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "title" => Ok(TagSource::Title),
+            "folder" => Ok(TagSource::Folder),
+            "filetags" => Ok(TagSource::Filetags),
+            _ => Err("no match"),
+        }
+    }
 }
 
 impl FromStr for ConflictHandling {
@@ -151,6 +478,7 @@ impl FromStr for ConflictHandling {
             "ignore" => Ok(ConflictHandling::Ignore),
             "pundit" => Ok(ConflictHandling::Pundit),
             "anki" => Ok(ConflictHandling::Anki),
+            "interactive" => Ok(ConflictHandling::Interactive),
             _ => Err("no match"),
         }
     }
@@ -159,14 +487,14 @@ impl FromStr for ConflictHandling {
 /// Add a pankit note by generating an id, allowing to interactively select model/deck and adding empty entries for all the fields.
 #[derive(Parser, Debug)]
 pub struct PankitGetNote {
-    /// The path of the anki database to get available model and fields from.
-    pub database: PathBuf,
+    /// The path of the anki database to get available model and fields from. Can be left out if the profile contains it.
+    pub database: Option<PathBuf>,
     /// Path to a pundit note. If this note contains anki notes, the model and deck from the first note will be used.
     /// If the note does not contain a pankit block, pundit will ask for the model and deck interactively.
     pub model_filename: Option<PathBuf>,
 }
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone, Copy)]
 pub enum ConflictHandling {
     /// Show an error if any conflict is encountered. Do not change anything in the database
     GiveError,
@@ -176,4 +504,6 @@ pub enum ConflictHandling {
     Anki,
     /// Blindly use the contents from pundit
     Pundit,
+    /// Show the differences for every conflicting note and ask which version to use
+    Interactive,
 }
@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use log::info;
+
+use crate::dir_utils::get_relative_path;
+use crate::note::Note;
+use crate::notes::Notes;
+use crate::settings::NoteSettings;
+
+pub struct LinkEdit {
+    pub old_link: String,
+    pub new_link: String,
+}
+
+/// The new contents of a note in which some links were replaced.
+pub struct FileEdit {
+    pub filename: PathBuf,
+    pub contents: String,
+    pub links: Vec<LinkEdit>,
+}
+
+impl FileEdit {
+    pub fn write(&self) -> Result<()> {
+        fs::write(&self.filename, &self.contents)
+            .context(format!("While updating links in {:?}", self.filename))
+    }
+
+    pub fn show(&self) {
+        info!("Update links in {}", self.filename.to_str().unwrap());
+        for link in self.links.iter() {
+            info!("\t{} -> {}", link.old_link, link.new_link);
+        }
+    }
+}
+
+/// Compute the new contents of every note linking to `note`. `replace` is called with the
+/// linking note and the link description and returns the text replacing the link.
+pub fn get_backlink_edits<F>(notes: &Notes, note: &Note, replace: F) -> Result<Vec<FileEdit>>
+where
+    F: Fn(&Note, &str) -> Result<String>,
+{
+    let mut backlinks = note.backlinks.clone();
+    backlinks.sort();
+    backlinks.dedup();
+    backlinks
+        .into_iter()
+        .map(|index| get_backlink_edit(&notes[index], note, &replace, &notes.settings))
+        .collect()
+}
+
+fn get_backlink_edit<F>(
+    backlink_note: &Note,
+    note: &Note,
+    replace: &F,
+    settings: &NoteSettings,
+) -> Result<FileEdit>
+where
+    F: Fn(&Note, &str) -> Result<String>,
+{
+    let parent_dir = backlink_note.filename.parent().unwrap();
+    let mut links = vec![];
+    let mut result = Ok(());
+    let format = backlink_note.format();
+    let contents = format.replace_links(
+        &backlink_note.get_contents()?,
+        |relative_link, description| {
+            if !links_to(parent_dir, relative_link, &note.filename) {
+                return None;
+            }
+            match replace(backlink_note, description) {
+                Ok(replacement) => {
+                    links.push(LinkEdit {
+                        old_link: format.format_link(relative_link, description, settings),
+                        new_link: replacement.clone(),
+                    });
+                    Some(replacement)
+                }
+                Err(err) => {
+                    result = Err(err);
+                    None
+                }
+            }
+        },
+    );
+    result?;
+    Ok(FileEdit {
+        filename: backlink_note.filename.clone(),
+        contents,
+        links,
+    })
+}
+
+/// A link from `backlink_note` to `target`, replacing a link that pointed to a note with the title `old_title`.
+/// The description is only replaced if it was the old title. Custom descriptions are kept.
+pub fn get_relinked_link(
+    target: &Note,
+    backlink_note: &Note,
+    old_title: &str,
+    description: &str,
+    settings: &NoteSettings,
+) -> Result<String> {
+    let relative_path =
+        get_relative_path(&target.filename, backlink_note.filename.parent().unwrap())?;
+    let new_description = match description == old_title {
+        true => &target.title,
+        false => description,
+    };
+    Ok(backlink_note
+        .format()
+        .format_link(&relative_path, new_description, settings))
+}
+
+fn links_to(parent_dir: &Path, relative_link: &Path, filename: &Path) -> bool {
+    parent_dir
+        .join(relative_link)
+        .canonicalize()
+        .map(|link| link == filename)
+        .unwrap_or(false)
+}
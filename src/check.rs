@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use log::info;
+use serde::Serialize;
+
+use crate::args::OutputFormat;
+use crate::note_format::NoteFormat;
+use crate::notes::get_note_files;
+use crate::notes::resolve_link;
+use crate::settings::NoteSettings;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    MissingTitle,
+    BrokenLink,
+    SelfLink,
+    DuplicateTitle,
+    Orphan,
+}
+
+impl ProblemKind {
+    /// Errors prevent pundit from reading the notes, warnings do not.
+    fn is_error(&self) -> bool {
+        matches!(
+            self,
+            ProblemKind::MissingTitle | ProblemKind::BrokenLink | ProblemKind::SelfLink
+        )
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Problem {
+    pub filename: PathBuf,
+    /// The (1-based) line number of the problem, if it can be pinned to a line.
+    pub line: Option<usize>,
+    pub kind: ProblemKind,
+    pub severity: &'static str,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(filename: &Path, line: Option<usize>, kind: ProblemKind, message: String) -> Problem {
+        Problem {
+            filename: filename.to_path_buf(),
+            line,
+            kind,
+            severity: match kind.is_error() {
+                true => "error",
+                false => "warning",
+            },
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.filename.to_str().unwrap())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+/// Check all note files in the folder and print every problem found.
+/// Returns an error if any of the problems would keep pundit from reading the notes.
+pub fn run_check(
+    note_folder: &Path,
+    multidir: bool,
+    output: &OutputFormat,
+    settings: &NoteSettings,
+) -> Result<()> {
+    let problems = check_notes(note_folder, multidir, settings)?;
+    match output {
+        OutputFormat::Text => {
+            for problem in problems.iter() {
+                info!("{}", problem);
+            }
+        }
+        OutputFormat::Json => info!("{}", serde_json::to_string_pretty(&problems)?),
+    }
+    let num_errors = problems
+        .iter()
+        .filter(|problem| problem.kind.is_error())
+        .count();
+    match num_errors {
+        0 => Ok(()),
+        _ => Err(anyhow!("Found {} errors in notes.", num_errors)),
+    }
+}
+
+/// Read the note files directly (without the database) and collect all problems.
+pub fn check_notes(
+    note_folder: &Path,
+    multidir: bool,
+    settings: &NoteSettings,
+) -> Result<Vec<Problem>> {
+    let files = get_note_files(note_folder, multidir)?;
+    let file_set: HashSet<&PathBuf> = files.iter().collect();
+    let mut problems = vec![];
+    let mut titles: HashMap<String, Vec<(&PathBuf, usize)>> = HashMap::new();
+    let mut linked: HashSet<PathBuf> = HashSet::new();
+    for file in files.iter() {
+        let contents =
+            fs::read_to_string(file).context(format!("Reading contents of {:?}", file))?;
+        let format = NoteFormat::from_filename(file).unwrap_or_default();
+        match format.get_title(&contents, settings) {
+            Ok(title) => {
+                let (line_number, _) = format.find_title_line(&contents).unwrap();
+                titles
+                    .entry(title)
+                    .or_default()
+                    .push((file, line_number + 1));
+            }
+            Err(err) => problems.push(Problem::new(
+                file,
+                Some(1),
+                ProblemKind::MissingTitle,
+                err.to_string(),
+            )),
+        }
+        let parent_dir = file.parent().unwrap();
+        for (line_number, line) in contents.split('\n').enumerate() {
+            for relative_link in format.get_link_filenames(line) {
+                let line = Some(line_number + 1);
+                match resolve_link(parent_dir, &relative_link, file) {
+                    Ok(target) if &target == file => problems.push(Problem::new(
+                        file,
+                        line,
+                        ProblemKind::SelfLink,
+                        "Note links to itself".to_owned(),
+                    )),
+                    Ok(target) if file_set.contains(&target) => {
+                        linked.insert(file.clone());
+                        linked.insert(target);
+                    }
+                    _ => problems.push(Problem::new(
+                        file,
+                        line,
+                        ProblemKind::BrokenLink,
+                        format!(
+                            "Link to {} does not point to a note",
+                            relative_link.to_str().unwrap()
+                        ),
+                    )),
+                }
+            }
+        }
+    }
+    for (title, notes) in titles.iter().filter(|(_, notes)| notes.len() > 1) {
+        for (file, line_number) in notes.iter() {
+            let others: Vec<&str> = notes
+                .iter()
+                .filter(|(other, _)| other != file)
+                .map(|(other, _)| other.to_str().unwrap())
+                .collect();
+            problems.push(Problem::new(
+                file,
+                Some(*line_number),
+                ProblemKind::DuplicateTitle,
+                format!("Title '{}' is also used by {}", title, others.join(", ")),
+            ));
+        }
+    }
+    for file in files.iter().filter(|file| !linked.contains(*file)) {
+        problems.push(Problem::new(
+            file,
+            None,
+            ProblemKind::Orphan,
+            "No links from or to this note".to_owned(),
+        ));
+    }
+    problems.sort_by(|p1, p2| (&p1.filename, p1.line).cmp(&(&p2.filename, p2.line)));
+    Ok(problems)
+}
@@ -1,8 +1,26 @@
 pub static LINK_FORMAT: &str = "[[file:{relative_path}][{title}]]";
 pub static TITLE_STRING: &str = "#+TITLE: ";
 pub static NOTE_EXTENSION: &str = "org";
-pub static NOTE_FILENAME_STR_FORMAT: &str = "{dateString}-{titleString}.org";
+pub static MARKDOWN_LINK_FORMAT: &str = "[{title}]({relative_path})";
+pub static MARKDOWN_TITLE_STRING: &str = "# ";
+pub static MARKDOWN_FRONT_MATTER_DELIMITER: &str = "---";
+pub static MARKDOWN_FRONT_MATTER_TITLE_STRING: &str = "title: ";
+pub static MARKDOWN_FRONT_MATTER_TAGS_STRING: &str = "tags:";
+pub static ORG_FILETAGS_STRING: &str = "#+FILETAGS: ";
+pub static ORG_TAGS_STRING: &str = "#+TAGS: ";
+pub static ORG_KEYWORD_PREFIX: &str = "#+";
+pub static MARKDOWN_NOTE_EXTENSION: &str = "md";
+pub static NOTE_FILENAME_STR_FORMAT: &str = "{dateString}-{titleString}.{extension}";
 pub static NOTE_DATE_FORMAT_STR: &str = "%Y%m%d%H%M%S";
+pub static SEARCH_SNIPPET_LENGTH: usize = 120;
+pub static SEARCH_INDEX_EXTENSION: &str = "search_index.json";
+pub static SETTINGS_FILE_NAME: &str = "settings.yaml";
+pub static SETTINGS_ENV_VAR: &str = "PUNDIT_CONFIG";
+pub static TEMPLATE_FOLDER_NAME: &str = "templates";
+pub static DEFAULT_TEMPLATE_NAME: &str = "default";
+pub static TEMPLATE_DATE_FORMAT_STR: &str = "%Y-%m-%d";
+pub static ORPHANED_DECK_NAME: &str = "orphaned";
+pub static ANKI_BACKUP_DATE_FORMAT_STR: &str = "%Y%m%d%H%M%S";
 pub static ID_MULTIPLIER: i64 = 100;
 pub static ANKI_BLOCK_NOTE_TEMPLATE: &str = "
 {id}:
@@ -15,7 +33,7 @@ model: {model}
 {fields}
 #+end_src";
 pub static ANKI_NOTE_FIELD_TEMPLATE: &str = "    {fieldName}: ";
-pub static JOURNAL_TITLE_FORMAT: &str = "{} {}";
+pub static JOURNAL_TITLE_FORMAT: &str = "{name} {date}";
 pub static JOURNAL_DATE_FORMAT_STR: &str = "%Y %m %d";
 pub static JOURNAL_IN_SUBFOLDERS: bool = true;
 
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use log::info;
+use serde::Serialize;
+
+use crate::args::Opts;
+use crate::config_opts::ConfigOpts;
+use crate::config_opts::ConfigSubCommand;
+use crate::settings::get_default_template_folder;
+use crate::settings::Settings;
+use crate::settings::SettingsLocation;
+
+/// The configuration pundit runs with, after applying the profile and the command line arguments.
+#[derive(Serialize)]
+struct EffectiveConfig<'a> {
+    settings_file: Option<PathBuf>,
+    profile: Option<String>,
+    folder: Option<PathBuf>,
+    database: Option<PathBuf>,
+    template_folder: Option<PathBuf>,
+    settings: &'a Settings,
+}
+
+pub fn run_config(
+    args: &ConfigOpts,
+    opts: &Opts,
+    settings: &Settings,
+    location: &SettingsLocation,
+) -> Result<()> {
+    match args.subcmd {
+        ConfigSubCommand::Show => {
+            let config = EffectiveConfig {
+                settings_file: get_existing_settings_file(location),
+                profile: opts.profile.clone(),
+                folder: opts.folder.clone(),
+                database: opts.database.clone(),
+                template_folder: opts
+                    .template_folder
+                    .clone()
+                    .or_else(get_default_template_folder),
+                settings,
+            };
+            info!(
+                "{}",
+                serde_yaml::to_string(&config).context("While converting settings to yaml")?
+            );
+        }
+        // The settings have already been read strictly at this point.
+        ConfigSubCommand::Validate => match get_existing_settings_file(location) {
+            Some(path) => info!("Settings in {:?} are valid.", path),
+            None => info!("No settings file found. Using default settings."),
+        },
+        ConfigSubCommand::Path => {
+            let path = location
+                .path()
+                .ok_or_else(|| anyhow!("Unable to determine the config directory"))?;
+            info!("{}", path.to_str().unwrap());
+        }
+    }
+    Ok(())
+}
+
+fn get_existing_settings_file(location: &SettingsLocation) -> Option<PathBuf> {
+    location
+        .path()
+        .filter(|path| path.is_file())
+        .map(|path| path.to_owned())
+}
@@ -0,0 +1,19 @@
+use clap::Parser;
+
+/// Inspect the settings.
+#[derive(Parser, Debug)]
+pub struct ConfigOpts {
+    #[clap(subcommand)]
+    pub subcmd: ConfigSubCommand,
+}
+
+#[derive(Parser, Debug)]
+pub enum ConfigSubCommand {
+    /// Print the effective configuration: the settings merged with the defaults, the selected profile
+    /// and the command line arguments
+    Show,
+    /// Read the settings file and give an error if it is invalid
+    Validate,
+    /// Print the path of the settings file
+    Path,
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::Local;
+use dirs_next::data_dir;
+use log::error;
+use log::info;
+
+use crate::args::BacklinkHandling;
+use crate::backlink_edit::get_backlink_edits;
+use crate::backlink_edit::get_relinked_link;
+use crate::backlink_edit::FileEdit;
+use crate::dir_utils::create_folder;
+use crate::file_utils::move_file;
+use crate::filter_options::get_num_backlinks;
+use crate::note::Note;
+use crate::note_utils::get_backlinks;
+use crate::notes::Notes;
+use crate::settings::NoteSettings;
+
+/// Delete a note. Links to the note in other notes are handled according to `backlink_handling`.
+pub fn delete_note(
+    notes: &Notes,
+    note: &Note,
+    backlink_handling: &BacklinkHandling,
+    replacement: Option<&Note>,
+    trash: bool,
+) -> Result<()> {
+    let backlink_edits = match backlink_handling {
+        BacklinkHandling::Refuse => {
+            if !note.backlinks.is_empty() {
+                show_backlinks(notes, note);
+                return Err(anyhow!(
+                    "Not deleting note with {} backlinks. Pass --backlinks unlink or --backlinks replace.",
+                    get_num_backlinks(note)
+                ));
+            }
+            vec![]
+        }
+        BacklinkHandling::Unlink => {
+            get_backlink_edits(notes, note, |_, description| Ok(description.to_owned()))?
+        }
+        BacklinkHandling::Replace => {
+            let replacement = replacement
+                .ok_or_else(|| anyhow!("No replacement note given to point the links to."))?;
+            get_replacement_edits(notes, note, replacement)?
+        }
+    };
+    for edit in backlink_edits.iter() {
+        edit.show();
+        edit.write()?;
+    }
+    match trash {
+        true => move_to_trash(&note.filename, &notes.settings),
+        false => delete_file(&note.filename),
+    }
+}
+
+fn show_backlinks(notes: &Notes, note: &Note) {
+    error!("There are links to this note: ");
+    for backlink_note in get_backlinks(notes, note) {
+        error!("\t{}", backlink_note.title);
+    }
+}
+
+fn get_replacement_edits(notes: &Notes, note: &Note, replacement: &Note) -> Result<Vec<FileEdit>> {
+    if replacement.filename == note.filename {
+        return Err(anyhow!("The replacement note is the note to delete."));
+    }
+    get_backlink_edits(notes, note, |backlink_note, description| {
+        // Pointing the link to the replacement would make the replacement link to itself.
+        match backlink_note.filename == replacement.filename {
+            true => Ok(description.to_owned()),
+            false => get_relinked_link(
+                replacement,
+                backlink_note,
+                &note.title,
+                description,
+                &notes.settings,
+            ),
+        }
+    })
+}
+
+fn delete_file(filename: &Path) -> Result<()> {
+    info!("Deleting {}", filename.to_str().unwrap());
+    fs::remove_file(filename).context(format!("While deleting {:?}", filename))
+}
+
+fn move_to_trash(filename: &Path, settings: &NoteSettings) -> Result<()> {
+    let trash_folder = get_trash_folder()?;
+    create_folder(&trash_folder)?;
+    let date_string = format!("{}", Local::now().format(&settings.date_format));
+    let target = get_trash_target(
+        &trash_folder,
+        &date_string,
+        filename.file_name().unwrap().to_str().unwrap(),
+    );
+    info!(
+        "Moving {} to {}",
+        filename.to_str().unwrap(),
+        target.to_str().unwrap()
+    );
+    move_file(filename, &target)
+}
+
+/// The date followed by the file name, with a counter in between if a note with the same
+/// file name was already moved to the trash at the same time.
+fn get_trash_target(trash_folder: &Path, date_string: &str, file_name: &str) -> PathBuf {
+    let mut target = trash_folder.join(format!("{}-{}", date_string, file_name));
+    let mut counter = 1;
+    while target.exists() {
+        target = trash_folder.join(format!("{}-{}-{}", date_string, counter, file_name));
+        counter += 1;
+    }
+    target
+}
+
+fn get_trash_folder() -> Result<PathBuf> {
+    Ok(data_dir()
+        .ok_or_else(|| anyhow!("Could not find data directory for the trash folder."))?
+        .join("pundit")
+        .join("trash"))
+}
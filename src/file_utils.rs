@@ -1,3 +1,4 @@
+use std::fs;
 use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::path::Path;
@@ -11,3 +12,25 @@ pub fn append_to_file(filename: &Path, content: &str) -> Result<()> {
     writeln!(file, "{}", content).context(format!("While appending to file {:?}", filename))?;
     Ok(())
 }
+
+/// Write the contents to a temporary file next to the file and rename it afterwards, so that
+/// the file is never left half-written.
+pub fn write_file_atomically(filename: &Path, contents: &str) -> Result<()> {
+    let mut temp_name = filename.file_name().unwrap().to_owned();
+    temp_name.push(".tmp");
+    let temp_filename = filename.with_file_name(temp_name);
+    fs::write(&temp_filename, contents).context(format!("While writing {:?}", temp_filename))?;
+    fs::rename(&temp_filename, filename).context(format!(
+        "While moving {:?} to {:?}",
+        temp_filename, filename
+    ))
+}
+
+/// Move a file, falling back to copying and removing if the target is on a different file system.
+pub fn move_file(source: &Path, target: &Path) -> Result<()> {
+    if fs::rename(source, target).is_err() {
+        fs::copy(source, target).context(format!("While copying {:?} to {:?}", source, target))?;
+        fs::remove_file(source).context(format!("While removing {:?}", source))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::NaiveDate;
+use regex::Regex;
+
+use crate::filter_options::get_num_backlinks;
+use crate::filter_options::note_is_created_between;
+use crate::filter_options::note_is_in_subfolder_recursively;
+use crate::filter_options::note_is_orphan;
+use crate::note::Note;
+use crate::notes::Notes;
+
+/// A combination of filters for notes, parsed from expressions
+/// such as "tag:physics and not (title:^draft or orphan)".
+#[derive(Debug)]
+pub enum FilterExpression {
+    Title(Regex),
+    Folder(PathBuf),
+    Tag(String),
+    After(NaiveDate),
+    Before(NaiveDate),
+    Orphan,
+    NoLinks,
+    MinBacklinks(usize),
+    And(Box<FilterExpression>, Box<FilterExpression>),
+    Or(Box<FilterExpression>, Box<FilterExpression>),
+    Not(Box<FilterExpression>),
+}
+
+impl FilterExpression {
+    pub fn includes_note(&self, notes: &Notes, note: &Note) -> bool {
+        match self {
+            FilterExpression::Title(regex) => regex.is_match(&note.title),
+            FilterExpression::Folder(folder) => {
+                note_is_in_subfolder_recursively(&notes.folder, folder, note)
+            }
+            FilterExpression::Tag(tag) => note.tags.contains(tag),
+            FilterExpression::After(date) => {
+                note_is_created_between(note, Some(*date), None, &notes.settings)
+            }
+            FilterExpression::Before(date) => {
+                note_is_created_between(note, None, Some(*date), &notes.settings)
+            }
+            FilterExpression::Orphan => note_is_orphan(note),
+            FilterExpression::NoLinks => note.links.is_empty(),
+            FilterExpression::MinBacklinks(num) => get_num_backlinks(note) >= *num,
+            FilterExpression::And(e1, e2) => {
+                e1.includes_note(notes, note) && e2.includes_note(notes, note)
+            }
+            FilterExpression::Or(e1, e2) => {
+                e1.includes_note(notes, note) || e2.includes_note(notes, note)
+            }
+            FilterExpression::Not(e) => !e.includes_note(notes, note),
+        }
+    }
+}
+
+impl FromStr for FilterExpression {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        let expression = parser.parse_or()?;
+        match parser.next() {
+            None => Ok(expression),
+            Some(token) => Err(anyhow!("Unexpected '{}' in filter expression", token)),
+        }
+    }
+}
+
+/// Split the expression at whitespace and parentheses, except within double quotes.
+fn tokenize(expression: &str) -> Result<Vec<String>> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut in_quotes = false;
+    for c in expression.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            _ if in_quotes => token.push(c),
+            '(' | ')' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+                tokens.push(c.to_string());
+            }
+            _ if c.is_whitespace() => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            _ => token.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(anyhow!("Unclosed quote in filter expression"));
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(|token| token.as_str())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpression> {
+        let mut expression = self.parse_and()?;
+        while self.peek() == Some("or") {
+            self.next();
+            expression = FilterExpression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpression> {
+        let mut expression = self.parse_not()?;
+        while self.peek() == Some("and") {
+            self.next();
+            expression = FilterExpression::And(Box::new(expression), Box::new(self.parse_not()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_not(&mut self) -> Result<FilterExpression> {
+        let token = self
+            .next()
+            .ok_or_else(|| anyhow!("Unexpected end of filter expression"))?;
+        match token.as_str() {
+            "not" => Ok(FilterExpression::Not(Box::new(self.parse_not()?))),
+            "(" => {
+                let expression = self.parse_or()?;
+                match self.next().as_deref() {
+                    Some(")") => Ok(expression),
+                    _ => Err(anyhow!("Missing ')' in filter expression")),
+                }
+            }
+            _ => parse_filter(&token),
+        }
+    }
+}
+
+fn parse_filter(token: &str) -> Result<FilterExpression> {
+    let (name, value) = match token.split_once(':') {
+        Some((name, value)) => (name, Some(value)),
+        None => (token, None),
+    };
+    let context = || format!("Invalid filter: {}", token);
+    match (name, value) {
+        ("title", Some(regex)) => Ok(FilterExpression::Title(
+            Regex::new(regex).with_context(context)?,
+        )),
+        ("folder", Some(folder)) => Ok(FilterExpression::Folder(PathBuf::from(folder))),
+        ("tag", Some(tag)) => Ok(FilterExpression::Tag(tag.to_owned())),
+        ("after", Some(date)) => Ok(FilterExpression::After(date.parse().with_context(context)?)),
+        ("before", Some(date)) => Ok(FilterExpression::Before(
+            date.parse().with_context(context)?,
+        )),
+        ("orphan", None) => Ok(FilterExpression::Orphan),
+        ("no-links", None) => Ok(FilterExpression::NoLinks),
+        ("min-backlinks", Some(num)) => Ok(FilterExpression::MinBacklinks(
+            num.parse().with_context(context)?,
+        )),
+        _ => Err(anyhow!("Unknown filter: {}", token)),
+    }
+}
@@ -1,23 +1,43 @@
 use std::path::Path;
 
+use chrono::NaiveDate;
 use clap::Parser;
 
 use crate::dir_utils::get_relative_path;
+use crate::filter_expression::FilterExpression;
+use crate::note::get_date_from_filename;
 use crate::note::Note;
+use crate::notes::Notes;
+use crate::settings::NoteSettings;
 /// Various options for filtering lists of notes
 #[derive(Parser, Debug)]
 pub enum FilterOptions {
     IncludeAll,
     FilterSubfolders(FilterSubfolders),
+    /// Only include notes which have all of the tags
+    WithTags(FilterTags),
+    /// Exclude notes which have any of the tags
+    WithoutTags(FilterTags),
+    /// Combine filters with and, or, not and parentheses, for example
+    /// "tag:physics and not (title:^draft or orphan)". Available filters are
+    /// title:<regex>, folder:<subfolder>, tag:<tag>, after:<YYYY-MM-DD>, before:<YYYY-MM-DD>,
+    /// orphan, no-links and min-backlinks:<number>. Values containing spaces or parentheses can be quoted.
+    Matching(FilterMatching),
 }
 
 impl FilterOptions {
-    pub fn includes_note(&self, base_folder: &Path, note: &Note) -> bool {
+    pub fn includes_note(&self, notes: &Notes, note: &Note) -> bool {
+        let base_folder = &notes.folder;
         match self {
             FilterOptions::IncludeAll => true,
             FilterOptions::FilterSubfolders(subfolders) => {
                 !note_is_in_any_subfolder(base_folder, note, subfolders)
             }
+            FilterOptions::WithTags(tags) => tags.tags.iter().all(|tag| note.tags.contains(tag)),
+            FilterOptions::WithoutTags(tags) => {
+                !tags.tags.iter().any(|tag| note.tags.contains(tag))
+            }
+            FilterOptions::Matching(matching) => matching.expression.includes_note(notes, note),
         }
     }
 }
@@ -40,7 +60,53 @@ fn note_is_in_subfolder(base_folder: &Path, subfolder: &Path, note: &Note) -> bo
     relative_path == subfolder
 }
 
+pub fn note_is_in_subfolder_recursively(base_folder: &Path, subfolder: &Path, note: &Note) -> bool {
+    let parent_folder = note.filename.parent().unwrap();
+    let relative_path = get_relative_path(parent_folder, base_folder).unwrap();
+    relative_path.starts_with(subfolder)
+}
+
+/// Whether the date in the filename of the note is on or after `after` and before `before`.
+/// Notes without a date in their filename are never included.
+pub fn note_is_created_between(
+    note: &Note,
+    after: Option<NaiveDate>,
+    before: Option<NaiveDate>,
+    settings: &NoteSettings,
+) -> bool {
+    match get_date_from_filename(&note.filename, settings) {
+        Some(date_time) => {
+            let date = date_time.date();
+            after.map(|after| date >= after).unwrap_or(true)
+                && before.map(|before| date < before).unwrap_or(true)
+        }
+        None => false,
+    }
+}
+
+pub fn note_is_orphan(note: &Note) -> bool {
+    note.links.is_empty() && note.backlinks.is_empty()
+}
+
+/// The number of different notes linking to the note.
+pub fn get_num_backlinks(note: &Note) -> usize {
+    let mut backlinks = note.backlinks.clone();
+    backlinks.sort();
+    backlinks.dedup();
+    backlinks.len()
+}
+
 #[derive(Parser, Debug)]
 pub struct FilterSubfolders {
     filter: Vec<String>,
 }
+
+#[derive(Parser, Debug)]
+pub struct FilterTags {
+    tags: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct FilterMatching {
+    expression: FilterExpression,
+}
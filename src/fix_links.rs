@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use log::error;
+
+use crate::backlink_edit::get_relinked_link;
+use crate::backlink_edit::FileEdit;
+use crate::backlink_edit::LinkEdit;
+use crate::note::get_date_from_filename;
+use crate::note::get_title_from_filename;
+use crate::note::Note;
+use crate::notes::get_note_files;
+use crate::settings::NoteSettings;
+
+/// Find all links which do not point to an existing note and point them to the note with the
+/// title given in the link description or, failing that, to the note with the same creation
+/// date in its filename. Custom link descriptions are kept. Reads the files directly, since the notes cannot be read while links are broken.
+pub fn fix_links(
+    note_folder: &Path,
+    multidir: bool,
+    dry_run: bool,
+    settings: &NoteSettings,
+) -> Result<()> {
+    let notes = read_notes_leniently(note_folder, multidir, settings)?;
+    let candidates = Candidates::new(&notes, settings);
+    for note in notes.iter() {
+        let edit = get_fixed_links_edit(note, &candidates)?;
+        if edit.links.is_empty() {
+            continue;
+        }
+        edit.show();
+        if !dry_run {
+            edit.write()?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the titles of all note files, skipping those without a valid title.
+fn read_notes_leniently(
+    note_folder: &Path,
+    multidir: bool,
+    settings: &NoteSettings,
+) -> Result<Vec<Note>> {
+    let mut notes = vec![];
+    for file in get_note_files(note_folder, multidir)? {
+        match Note::from_filename_no_links(&file, settings) {
+            Ok(note) => notes.push(note),
+            Err(err) => error!("Skipping {:?}: {}", file, err),
+        }
+    }
+    Ok(notes)
+}
+
+/// The notes a dangling link could be pointed to. Titles or dates that
+/// belong to more than one note are ambiguous and not used.
+struct Candidates<'a> {
+    filenames: HashSet<&'a Path>,
+    by_title: HashMap<&'a str, &'a Note>,
+    by_date: HashMap<NaiveDateTime, &'a Note>,
+    settings: &'a NoteSettings,
+}
+
+impl<'a> Candidates<'a> {
+    fn new(notes: &'a [Note], settings: &'a NoteSettings) -> Candidates<'a> {
+        Candidates {
+            filenames: notes.iter().map(|note| note.filename.as_path()).collect(),
+            by_title: get_unique_map(notes, |note| Some(note.title.as_str())),
+            by_date: get_unique_map(notes, |note| {
+                get_date_from_filename(&note.filename, settings)
+            }),
+            settings,
+        }
+    }
+
+    fn find(&self, relative_link: &Path, description: &str) -> Option<&'a Note> {
+        self.by_title.get(description).copied().or_else(|| {
+            get_date_from_filename(relative_link, self.settings)
+                .and_then(|date| self.by_date.get(&date).copied())
+        })
+    }
+}
+
+fn get_unique_map<'a, K, F>(notes: &'a [Note], get_key: F) -> HashMap<K, &'a Note>
+where
+    K: Hash + Eq,
+    F: Fn(&'a Note) -> Option<K>,
+{
+    let mut map: HashMap<K, Vec<&Note>> = HashMap::new();
+    for note in notes.iter() {
+        if let Some(key) = get_key(note) {
+            map.entry(key).or_default().push(note);
+        }
+    }
+    map.into_iter()
+        .filter(|(_, notes)| notes.len() == 1)
+        .map(|(key, notes)| (key, notes[0]))
+        .collect()
+}
+
+fn get_fixed_links_edit(note: &Note, candidates: &Candidates) -> Result<FileEdit> {
+    let parent_dir = note.filename.parent().unwrap();
+    let format = note.format();
+    let mut links = vec![];
+    let mut result = Ok(());
+    let contents = format.replace_links(&note.get_contents()?, |relative_link, description| {
+        if is_valid_link(parent_dir, relative_link, candidates) {
+            return None;
+        }
+        let target = candidates
+            .find(relative_link, description)
+            .filter(|target| target.filename != note.filename);
+        let target = match target {
+            Some(target) => target,
+            None => {
+                error!(
+                    "No note found for link to {} in {}",
+                    relative_link.to_str().unwrap(),
+                    note.filename.to_str().unwrap()
+                );
+                return None;
+            }
+        };
+        let old_title =
+            get_title_from_filename(relative_link, candidates.settings).unwrap_or_default();
+        match get_relinked_link(target, note, &old_title, description, candidates.settings) {
+            Ok(new_link) => {
+                links.push(LinkEdit {
+                    old_link: format.format_link(relative_link, description, candidates.settings),
+                    new_link: new_link.clone(),
+                });
+                Some(new_link)
+            }
+            Err(err) => {
+                result = Err(err);
+                None
+            }
+        }
+    });
+    result?;
+    Ok(FileEdit {
+        filename: note.filename.clone(),
+        contents,
+        links,
+    })
+}
+
+fn is_valid_link(parent_dir: &Path, relative_link: &Path, candidates: &Candidates) -> bool {
+    parent_dir
+        .join(relative_link)
+        .canonicalize()
+        .map(|link| candidates.filenames.contains(link.as_path()))
+        .unwrap_or(false)
+}
@@ -1,33 +1,229 @@
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::str::FromStr;
 
+use clap::Parser;
 use generational_arena::Index;
 
 use crate::note::Note;
 use crate::notes::Notes;
 
 pub fn get_connected_component_undirected<'a>(notes: &'a Notes, note: &'a Note) -> Vec<&'a Note> {
-    let mut visited = HashMap::new();
-    for (i, n) in notes.index_iter() {
-        visited.insert(i, n.filename == note.filename);
-    }
-    depth_first_search(&mut visited, notes, note, 0);
+    get_neighbourhood(notes, note, Direction::Both, None, |_| false)
+}
+
+/// All notes which can be reached from `note` by following at most `max_depth` links
+/// (or any number if it is None) in the given direction, including `note` itself.
+/// Notes for which `is_excluded` returns true are neither included nor walked through.
+pub fn get_neighbourhood<'a, F>(
+    notes: &'a Notes,
+    note: &Note,
+    direction: Direction,
+    max_depth: Option<usize>,
+    is_excluded: F,
+) -> Vec<&'a Note>
+where
+    F: Fn(&Note) -> bool,
+{
+    let visited = breadth_first_search_limited(notes, note, direction, max_depth, is_excluded);
     notes
         .index_iter()
-        .filter(|(i, _)| visited[i])
+        .filter(|(i, _)| visited.contains_key(i))
         .map(|(_, note)| note)
         .collect()
 }
 
-fn depth_first_search<'a, 'b>(
-    visited: &'b mut HashMap<Index, bool>,
+/// Which links to follow when walking the graph.
+#[derive(Parser, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Follow links from a note to the notes it links to
+    Out,
+    /// Follow links from a note to the notes linking to it
+    In,
+    /// Follow links in both directions
+    Both,
+}
+
+impl FromStr for Direction {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "out" => Ok(Direction::Out),
+            "in" => Ok(Direction::In),
+            "both" => Ok(Direction::Both),
+            _ => Err("no match"),
+        }
+    }
+}
+
+fn get_neighbours(note: &Note, direction: Direction) -> Vec<Index> {
+    match direction {
+        Direction::Out => note.links.clone(),
+        Direction::In => note.backlinks.clone(),
+        Direction::Both => note
+            .links
+            .iter()
+            .chain(note.backlinks.iter())
+            .copied()
+            .collect(),
+    }
+}
+
+/// Run a breadth first search starting at `note`. Returns the predecessor of every reachable
+/// note on a shortest path from `note` along with the distance to `note`.
+fn breadth_first_search(
+    notes: &Notes,
+    note: &Note,
+    direction: Direction,
+) -> HashMap<Index, (Option<Index>, usize)> {
+    breadth_first_search_limited(notes, note, direction, None, |_| false)
+}
+
+/// Like `breadth_first_search`, but stop at `max_depth` links from `note`
+/// and skip all notes for which `is_excluded` returns true.
+fn breadth_first_search_limited<F>(
+    notes: &Notes,
+    note: &Note,
+    direction: Direction,
+    max_depth: Option<usize>,
+    is_excluded: F,
+) -> HashMap<Index, (Option<Index>, usize)>
+where
+    F: Fn(&Note) -> bool,
+{
+    let mut visited = HashMap::new();
+    let start = match notes.find_index_by_filename(&note.filename) {
+        Some(start) => start,
+        None => return visited,
+    };
+    visited.insert(start, (None, 0));
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(index) = queue.pop_front() {
+        let distance = visited[&index].1;
+        if max_depth == Some(distance) {
+            continue;
+        }
+        for neighbour in get_neighbours(&notes[index], direction) {
+            if is_excluded(&notes[neighbour]) {
+                continue;
+            }
+            if let Entry::Vacant(entry) = visited.entry(neighbour) {
+                entry.insert((Some(index), distance + 1));
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    visited
+}
+
+/// The number of links between `note` and every note reachable from it.
+pub fn get_distances(notes: &Notes, note: &Note, direction: Direction) -> HashMap<Index, usize> {
+    breadth_first_search(notes, note, direction)
+        .into_iter()
+        .map(|(index, (_, distance))| (index, distance))
+        .collect()
+}
+
+/// The notes along a shortest path from `start` to `end`, including both.
+pub fn get_shortest_path<'a>(
     notes: &'a Notes,
-    note: &'a Note,
-    level: i32,
-) {
-    for index in note.links.iter().chain(note.backlinks.iter()) {
-        if !visited[index] {
-            visited.insert(*index, true);
-            depth_first_search(visited, notes, &notes[*index], level + 1);
+    start: &Note,
+    end: &Note,
+    direction: Direction,
+) -> Option<Vec<&'a Note>> {
+    let visited = breadth_first_search(notes, start, direction);
+    let mut index = notes.find_index_by_filename(&end.filename)?;
+    let mut path = vec![&notes[index]];
+    while let (Some(predecessor), _) = visited.get(&index)? {
+        index = *predecessor;
+        path.push(&notes[index]);
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// The connected components of the graph (ignoring link directions), largest first.
+pub fn get_connected_components(notes: &Notes) -> Vec<Vec<Index>> {
+    let mut visited = HashSet::new();
+    let mut components = vec![];
+    for (index, note) in notes.index_iter() {
+        if visited.contains(&index) {
+            continue;
         }
+        let component: Vec<Index> = breadth_first_search(notes, note, Direction::Both)
+            .into_keys()
+            .collect();
+        visited.extend(component.iter().copied());
+        components.push(component);
     }
+    components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+    components
+}
+
+/// The notes whose removal would split their connected component into several
+/// (ignoring link directions). Uses an iterative version of Tarjan's algorithm.
+pub fn get_articulation_points(notes: &Notes) -> HashSet<Index> {
+    let mut discovery: HashMap<Index, usize> = HashMap::new();
+    let mut low: HashMap<Index, usize> = HashMap::new();
+    let mut articulation_points = HashSet::new();
+    for (root, _) in notes.index_iter() {
+        if discovery.contains_key(&root) {
+            continue;
+        }
+        discovery.insert(root, discovery.len());
+        low.insert(root, discovery[&root]);
+        let mut num_root_children = 0;
+        // The visited note, its parent in the search tree, its neighbours and the next neighbour to visit
+        let mut stack = vec![(root, None, get_undirected_neighbours(notes, root), 0)];
+        while let Some((index, parent, neighbours, position)) = stack.last_mut() {
+            let index = *index;
+            let parent = *parent;
+            match neighbours.get(*position).copied() {
+                Some(neighbour) => {
+                    *position += 1;
+                    if Some(neighbour) == parent {
+                        continue;
+                    }
+                    match discovery.get(&neighbour).copied() {
+                        Some(neighbour_discovery) => {
+                            low.insert(index, low[&index].min(neighbour_discovery));
+                        }
+                        None => {
+                            discovery.insert(neighbour, discovery.len());
+                            low.insert(neighbour, discovery[&neighbour]);
+                            if index == root {
+                                num_root_children += 1;
+                            }
+                            let neighbours = get_undirected_neighbours(notes, neighbour);
+                            stack.push((neighbour, Some(index), neighbours, 0));
+                        }
+                    }
+                }
+                None => {
+                    stack.pop();
+                    if let Some(parent) = parent {
+                        low.insert(parent, low[&parent].min(low[&index]));
+                        if parent != root && low[&index] >= discovery[&parent] {
+                            articulation_points.insert(parent);
+                        }
+                    }
+                }
+            }
+        }
+        if num_root_children > 1 {
+            articulation_points.insert(root);
+        }
+    }
+    articulation_points
+}
+
+fn get_undirected_neighbours(notes: &Notes, index: Index) -> Vec<Index> {
+    let mut neighbours = get_neighbours(&notes[index], Direction::Both);
+    neighbours.sort();
+    neighbours.dedup();
+    neighbours.retain(|neighbour| *neighbour != index);
+    neighbours
 }
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use anyhow::Result;
+use generational_arena::Index;
+use log::info;
+use serde::Serialize;
+
+use crate::args::GraphFormat;
+use crate::dir_utils::get_relative_path;
+use crate::note::Note;
+use crate::notes::Notes;
+
+#[derive(Serialize)]
+pub struct GraphNode {
+    pub id: usize,
+    pub title: String,
+    /// The path of the note relative to the note folder
+    pub path: String,
+    /// The folder of the note relative to the note folder
+    pub folder: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct GraphEdge {
+    pub source: usize,
+    pub target: usize,
+}
+
+#[derive(Serialize)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl Graph {
+    /// The graph of links between the given notes, ordered by filename.
+    /// Links to notes which are not part of the graph are left out.
+    pub fn from_notes(all_notes: &Notes, notes: &[&Note]) -> Result<Graph> {
+        let mut notes = notes.to_vec();
+        notes.sort_by(|n1, n2| n1.filename.cmp(&n2.filename));
+        let ids: HashMap<Index, usize> = notes
+            .iter()
+            .enumerate()
+            .filter_map(|(id, note)| {
+                all_notes
+                    .find_index_by_filename(&note.filename)
+                    .map(|index| (index, id))
+            })
+            .collect();
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        for (id, note) in notes.iter().enumerate() {
+            let path = get_relative_path(&note.filename, &all_notes.folder)?;
+            nodes.push(GraphNode {
+                id,
+                title: note.title.clone(),
+                path: path.to_str().unwrap().to_owned(),
+                folder: path.parent().unwrap().to_str().unwrap().to_owned(),
+                tags: note.tags.clone(),
+            });
+            let mut targets: Vec<usize> = note
+                .links
+                .iter()
+                .filter_map(|link| ids.get(link).copied())
+                .collect();
+            targets.sort_unstable();
+            targets.dedup();
+            edges.extend(
+                targets
+                    .into_iter()
+                    .map(|target| GraphEdge { source: id, target }),
+            );
+        }
+        Ok(Graph { nodes, edges })
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec!["digraph notes {".to_owned()];
+        for node in self.nodes.iter() {
+            lines.push(format!(
+                "    {} [label=\"{}\", path=\"{}\", folder=\"{}\", tags=\"{}\"];",
+                node.id,
+                escape_dot(&node.title),
+                escape_dot(&node.path),
+                escape_dot(&node.folder),
+                escape_dot(&node.tags.join(","))
+            ));
+        }
+        for edge in self.edges.iter() {
+            lines.push(format!("    {} -> {};", edge.source, edge.target));
+        }
+        lines.push("}".to_owned());
+        lines.join("\n")
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let mut lines = vec![
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_owned(),
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">".to_owned(),
+        ];
+        for key in ["title", "path", "folder", "tags"] {
+            lines.push(format!(
+                "  <key id=\"{}\" for=\"node\" attr.name=\"{}\" attr.type=\"string\"/>",
+                key, key
+            ));
+        }
+        lines.push("  <graph id=\"notes\" edgedefault=\"directed\">".to_owned());
+        for node in self.nodes.iter() {
+            lines.push(format!("    <node id=\"n{}\">", node.id));
+            let data = [
+                ("title", node.title.clone()),
+                ("path", node.path.clone()),
+                ("folder", node.folder.clone()),
+                ("tags", node.tags.join(",")),
+            ];
+            for (key, value) in data.iter() {
+                lines.push(format!(
+                    "      <data key=\"{}\">{}</data>",
+                    key,
+                    escape_xml(value)
+                ));
+            }
+            lines.push("    </node>".to_owned());
+        }
+        for edge in self.edges.iter() {
+            lines.push(format!(
+                "    <edge source=\"n{}\" target=\"n{}\"/>",
+                edge.source, edge.target
+            ));
+        }
+        lines.push("  </graph>".to_owned());
+        lines.push("</graphml>".to_owned());
+        lines.join("\n")
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("While converting graph to json")
+    }
+
+    pub fn format(&self, format: &GraphFormat) -> Result<String> {
+        match format {
+            GraphFormat::Dot => Ok(self.to_dot()),
+            GraphFormat::Graphml => Ok(self.to_graphml()),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+}
+
+/// Write the graph of the notes to the output file or, if there is none, to stdout.
+pub fn export_graph(
+    all_notes: &Notes,
+    notes: &[&Note],
+    format: &GraphFormat,
+    output: Option<&Path>,
+) -> Result<()> {
+    let contents = Graph::from_notes(all_notes, notes)?.format(format)?;
+    match output {
+        Some(output) => {
+            fs::write(output, contents).context(format!("While writing graph to {:?}", output))
+        }
+        None => {
+            info!("{}", contents);
+            Ok(())
+        }
+    }
+}
+
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -14,9 +14,19 @@ use crate::note_arg::NoteArg;
 use crate::note_utils::find_or_create_note_with_special_content;
 use crate::note_utils::get_backlinks;
 use crate::notes::Notes;
+use crate::settings::JournalSettings;
+use crate::template::get_new_note_content;
+use crate::template::Template;
+use crate::template::TemplateValues;
+use crate::template::Templates;
 
-pub fn run_journal(notes: &mut Notes, args: &JournalOpts) -> Result<()> {
-    let journal_info = JournalInfo::from_name(notes, &args.name)?;
+pub fn run_journal(
+    notes: &mut Notes,
+    args: &JournalOpts,
+    templates: &Templates,
+    settings: &JournalSettings,
+) -> Result<()> {
+    let journal_info = JournalInfo::from_name(notes, &args.name, settings)?;
     let target_date = match &args.subcmd {
         // args::JournalSubCommand::Find => {}
         JournalSubCommand::Yesterday => get_date_yesterday()?,
@@ -33,7 +43,17 @@ pub fn run_journal(notes: &mut Notes, args: &JournalOpts) -> Result<()> {
             get_date_via_selector(notes, &journal_info, n, day_after_date)?
         }
     };
-    let note = find_or_create_journal_note_for_date(notes, &journal_info, &target_date)?;
+    let template = templates.find(
+        &notes.folder,
+        &journal_info.folder,
+        args.template.as_deref(),
+    )?;
+    let note = find_or_create_journal_note_for_date(
+        notes,
+        &journal_info,
+        &target_date,
+        template.as_ref(),
+    )?;
     match args.date {
         false => note.show_filename(),
         true => info!("{}", target_date),
@@ -45,11 +65,18 @@ fn find_or_create_journal_note_for_date<'a>(
     notes: &'a mut Notes,
     journal: &JournalInfo,
     date_time: &NaiveDate,
+    template: Option<&Template>,
 ) -> Result<&'a Note> {
     let title = journal.get_note_title_from_date(date_time);
-    let link_text = &journal.get_link_text_to_base_note(notes)?;
+    let content = get_new_note_content(
+        notes,
+        &journal.folder,
+        template,
+        &TemplateValues::new(&title, *date_time),
+        &journal.get_link_text_to_base_note(notes)?,
+    )?;
     let target_note =
-        find_or_create_note_with_special_content(notes, &journal.folder, &title, link_text)?;
+        find_or_create_note_with_special_content(notes, &journal.folder, &title, &content)?;
     Ok(target_note)
 }
 
@@ -86,14 +113,14 @@ where
     let input_note = note_name
         .find_in(notes)
         .ok_or_else(|| anyhow!("Input note not found: {:?}", note_name))?;
-    let input_date = JournalInfo::get_date_from_note(input_note)?;
+    let input_date = journal.get_date_from_note(input_note)?;
     Ok(select(&dates, &input_date))
 }
 
 fn get_all_entry_dates(notes: &Notes, journal: &JournalInfo) -> Result<Vec<NaiveDate>> {
     let journal_base_note = journal.get_base_note(notes);
     get_backlinks(notes, journal_base_note)
-        .map(|note| JournalInfo::get_date_from_note(note))
+        .map(|note| journal.get_date_from_note(note))
         .collect()
 }
 
@@ -5,26 +5,34 @@ use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
 use chrono::NaiveDate;
+use regex::Regex;
 
-use crate::config;
 use crate::dir_utils::create_folder;
 use crate::note::Note;
+use crate::note_format::NoteFormat;
 use crate::note_utils::find_or_create_note;
 use crate::notes::Notes;
+use crate::settings::JournalSettings;
 
 pub struct JournalInfo {
     pub name: String,
     pub folder: PathBuf,
+    settings: JournalSettings,
 }
 
 impl JournalInfo {
-    pub fn from_name(notes: &mut Notes, name: &str) -> Result<JournalInfo> {
-        let folder = get_folder(&notes.folder, name);
+    pub fn from_name(
+        notes: &mut Notes,
+        name: &str,
+        settings: &JournalSettings,
+    ) -> Result<JournalInfo> {
+        let folder = get_folder(&notes.folder, name, settings);
         ensure_journal_folder_exists(&folder)?;
         ensure_journal_base_note_exists(notes, &folder, name)?;
         Ok(JournalInfo {
             name: name.to_owned(),
             folder: folder.clone(),
+            settings: settings.clone(),
             // base_note: get_journal_base_note(notes, &folder, name)?,
         })
     }
@@ -33,26 +41,32 @@ impl JournalInfo {
         let base_note = self.get_base_note(notes);
         Ok(format!(
             "\n{}",
-            base_note.get_link_from_folder(&self.folder)?
+            base_note.get_link_from_folder(&self.folder, NoteFormat::default(), &notes.settings)?
         ))
     }
 
     pub fn get_note_title_from_date(&self, date: &NaiveDate) -> String {
-        assert_eq!(config::JOURNAL_TITLE_FORMAT, "{} {}");
-        format!(
-            "{} {}",
-            self.name,
-            date.format(config::JOURNAL_DATE_FORMAT_STR)
-        )
+        self.settings
+            .title_format
+            .replace("{name}", &self.name)
+            .replace(
+                "{date}",
+                &date.format(&self.settings.date_format).to_string(),
+            )
     }
 
-    pub fn get_date_from_note(note: &Note) -> Result<NaiveDate> {
-        JournalInfo::get_date_from_note_title(&note.title)
+    pub fn get_date_from_note(&self, note: &Note) -> Result<NaiveDate> {
+        self.get_date_from_note_title(&note.title)
     }
 
-    fn get_date_from_note_title(title: &str) -> Result<NaiveDate> {
-        assert_eq!(config::JOURNAL_TITLE_FORMAT, "{} {}"); // This needs to be changed when the format string changes.
-        let date_part_of_title = title.split_once(" ").map(|(_, s1)| s1);
+    fn get_date_from_note_title(&self, title: &str) -> Result<NaiveDate> {
+        let pattern = regex::escape(&self.settings.title_format)
+            .replace(r"\{name\}", &regex::escape(&self.name))
+            .replace(r"\{date\}", "(?P<date>.+)");
+        let date_part_of_title = Regex::new(&format!("^{}$", pattern))
+            .unwrap()
+            .captures(title)
+            .map(|captures| captures["date"].to_owned());
         date_part_of_title
             .ok_or_else(|| {
                 anyhow!(format!(
@@ -61,7 +75,7 @@ impl JournalInfo {
                 ))
             })
             .and_then(|date_str| {
-                NaiveDate::parse_from_str(date_str, config::JOURNAL_DATE_FORMAT_STR)
+                NaiveDate::parse_from_str(&date_str, &self.settings.date_format)
                     .context(format!("Invalid date in title for journal note: {}", title))
             })
     }
@@ -71,8 +85,12 @@ impl JournalInfo {
     }
 }
 
-fn get_folder<'a>(base_folder: &'a Path, journal_name: &'a str) -> PathBuf {
-    let folder = match config::JOURNAL_IN_SUBFOLDERS {
+fn get_folder<'a>(
+    base_folder: &'a Path,
+    journal_name: &'a str,
+    settings: &JournalSettings,
+) -> PathBuf {
+    let folder = match settings.in_subfolders {
         true => base_folder.join(journal_name),
         false => base_folder.to_path_buf(),
     };
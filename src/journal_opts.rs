@@ -10,6 +10,9 @@ pub struct JournalOpts {
     /// Print the date of the target journal note, not the filename
     #[clap(short, long)]
     pub date: bool,
+    /// The name of the template for new entries. By default, the template for the journal folder is used, if there is one.
+    #[clap(long)]
+    pub template: Option<String>,
 
     #[clap(subcommand)]
     pub subcmd: JournalSubCommand,
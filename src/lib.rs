@@ -1,20 +1,37 @@
 pub mod anki;
 pub mod args;
+pub mod backlink_edit;
+pub mod check;
 pub mod config;
+pub mod config_command;
+pub mod config_opts;
+pub mod delete;
 pub mod dir_utils;
 pub mod file_utils;
+pub mod filter_expression;
 pub mod filter_options;
+pub mod fix_links;
 pub mod fzf;
 pub mod graph;
+pub mod graph_export;
 pub mod journal;
 pub mod journal_info;
 pub mod journal_opts;
 pub mod named;
 pub mod note;
 pub mod note_arg;
+pub mod note_format;
 pub mod note_utils;
 pub mod notes;
+pub mod notes_sqlite;
 pub mod pankit;
 pub mod paper;
 pub mod paper_opts;
+pub mod rename;
+pub mod search;
+pub mod search_index;
 pub mod settings;
+pub mod stats;
+pub mod suggest_links;
+pub mod tags;
+pub mod template;
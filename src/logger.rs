@@ -22,9 +22,11 @@ impl log::Log for ResultLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            match self.add_identifier {
-                true => println!("\nOUTPUT{}", record.args()),
-                false => println!("{}", record.args()),
+            // Warnings are not part of the output, so that they do not end up in piped results.
+            match (record.level(), self.add_identifier) {
+                (Level::Warn, _) => eprintln!("{}", record.args()),
+                (_, true) => println!("\nOUTPUT{}", record.args()),
+                (_, false) => println!("{}", record.args()),
             }
         }
     }
@@ -10,18 +10,20 @@ use anyhow::Context;
 use anyhow::Result;
 use chrono::DateTime;
 use chrono::Local;
+use chrono::NaiveDateTime;
+use chrono::TimeZone;
 use generational_arena::Index;
 use log::info;
+use regex::Captures;
 use regex::Regex;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::config::LINK_FORMAT;
-use crate::config::NOTE_DATE_FORMAT_STR;
-use crate::config::NOTE_FILENAME_STR_FORMAT;
-use crate::config::TITLE_STRING;
+use crate::config::ORG_KEYWORD_PREFIX;
 use crate::dir_utils::get_relative_path;
+use crate::note_format::NoteFormat;
 use crate::notes::Notes;
+use crate::settings::NoteSettings;
 
 #[derive(Debug, Clone)]
 struct InvalidNoteError;
@@ -34,25 +36,44 @@ pub struct Note {
     pub title: String,
     pub links: Vec<Index>,
     pub backlinks: Vec<Index>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl Note {
-    pub fn from_filename_no_links(filename: &Path) -> Result<Note> {
-        let contents = Note::get_first_line(filename)
-            .context(format!("Reading contents of {:?}", filename))?;
+    pub fn from_filename_no_links(filename: &Path, settings: &NoteSettings) -> Result<Note> {
+        let format = NoteFormat::from_filename(filename).unwrap_or_default();
+        let contents = match format.has_header_keywords() {
+            true => Note::get_header(filename),
+            false => fs::read_to_string(filename).map_err(|err| err.into()),
+        }
+        .context(format!("Reading contents of {:?}", filename))?;
+        Note::from_contents(filename, &contents, settings)
+    }
+
+    /// Read title and tags from the (possibly partial) contents of the note file.
+    pub fn from_contents(filename: &Path, contents: &str, settings: &NoteSettings) -> Result<Note> {
+        let format = NoteFormat::from_filename(filename).unwrap_or_default();
         Ok(Note {
             filename: filename.to_owned(),
-            title: get_title(&contents)
+            title: format
+                .get_title(contents, settings)
                 .context(format!("Opening {}", filename.to_str().unwrap()))?,
             links: vec![],
             backlinks: vec![],
+            tags: format.get_tags(contents),
         })
     }
 
-    pub fn from_folder_and_title(folder: &Path, title: &str) -> Note {
+    pub fn from_folder_and_title(folder: &Path, title: &str, settings: &NoteSettings) -> Note {
         let date_time = Local::now();
         let filename = Path::new(folder)
-            .join(&get_filename_from_title(&title, date_time))
+            .join(get_filename_from_title(
+                &title,
+                date_time,
+                NoteFormat::default(),
+                settings,
+            ))
             .to_path_buf();
         Note::empty_note(filename, title)
     }
@@ -63,37 +84,70 @@ impl Note {
             title: title.to_string(),
             links: vec![],
             backlinks: vec![],
+            tags: vec![],
         }
     }
 
-    pub fn write_without_contents(&self) -> std::io::Result<()> {
+    pub fn write_without_contents(&self, settings: &NoteSettings) -> std::io::Result<()> {
         let mut file = File::create(&self.filename)?;
-        let contents = get_title_string(&self.title);
+        let contents = self.format().get_title_string(&self.title, settings);
         file.write_all(contents.as_bytes())?;
         Ok(())
     }
 
-    pub fn get_first_line(filename: &Path) -> Result<String> {
+    /// Read the first line and all directly following org keyword lines.
+    pub fn get_header(filename: &Path) -> Result<String> {
         let file = fs::File::open(filename)?;
-        let mut buffer = BufReader::new(file);
-        let mut first_line = String::new();
-        buffer.read_line(&mut first_line)?;
-        Ok(first_line)
+        let mut header = String::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if i > 0 && !line.starts_with(ORG_KEYWORD_PREFIX) {
+                break;
+            }
+            header.push_str(&line);
+            header.push('\n');
+        }
+        Ok(header)
     }
 
     pub fn get_contents(&self) -> Result<String> {
         fs::read_to_string(&self.filename).context("While reading file")
     }
 
-    pub fn get_link_from(&self, note1: &Note) -> Result<String> {
-        self.get_link_from_folder(note1.filename.parent().unwrap())
+    pub fn format(&self) -> NoteFormat {
+        NoteFormat::from_filename(&self.filename).unwrap_or_default()
+    }
+
+    pub fn get_link_from(&self, note1: &Note, settings: &NoteSettings) -> Result<String> {
+        self.get_link_from_folder(note1.filename.parent().unwrap(), note1.format(), settings)
     }
 
-    pub fn get_link_from_folder(&self, folder: &Path) -> Result<String> {
+    /// The text of a link to this note written into a note of the given format in the folder.
+    pub fn get_link_from_folder(
+        &self,
+        folder: &Path,
+        format: NoteFormat,
+        settings: &NoteSettings,
+    ) -> Result<String> {
         let relative_path = get_relative_path(&self.filename, folder)?;
-        Ok(LINK_FORMAT
-            .replace("{relative_path}", relative_path.to_str().unwrap())
-            .replace("{title}", &self.title))
+        Ok(format.format_link(&relative_path, &self.title, settings))
+    }
+
+    /// The filename this note would have if it had the given title. The creation date
+    /// in the filename is kept, so that renaming a note does not change its position in time.
+    pub fn get_filename_for_title(&self, title: &str, settings: &NoteSettings) -> PathBuf {
+        let date_time = get_date_from_filename(&self.filename, settings)
+            .and_then(|date_time| Local.from_local_datetime(&date_time).single())
+            .unwrap_or_else(Local::now);
+        self.filename
+            .parent()
+            .unwrap()
+            .join(get_filename_from_title(
+                title,
+                date_time,
+                self.format(),
+                settings,
+            ))
     }
 
     pub fn show_filename(&self) {
@@ -104,35 +158,43 @@ impl Note {
     }
 }
 
-fn get_title(first_line: &str) -> Result<String> {
-    if !first_line.starts_with(TITLE_STRING) {
-        Err(anyhow!("Note does not contain title"))
-    } else {
-        let title = first_line
-            .strip_prefix(TITLE_STRING)
-            .ok_or_else(|| anyhow!(format!("Invalid title string: {}", first_line)))?
-            .trim_end_matches("\n");
-        Ok(title.to_string())
-    }
+/// Read the creation date from a filename of the form given by the filename format in the settings.
+pub fn get_date_from_filename(filename: &Path, settings: &NoteSettings) -> Option<NaiveDateTime> {
+    let file_name = filename.file_name()?.to_str()?;
+    let captures = get_filename_captures(file_name, settings)?;
+    settings.parse_date(captures.name("date")?.as_str())
 }
 
-pub fn get_link_filenames(contents: &str) -> Vec<PathBuf> {
-    let re = Regex::new(r"\[\[file:(.*?)\]\[(.*?)\]\]").unwrap();
-    re.captures_iter(contents)
-        .map(|cap| Path::new(&cap[1].to_string()).to_path_buf())
-        .collect()
+/// Read the title from a filename of the form given by the filename format in the settings.
+pub fn get_title_from_filename(filename: &Path, settings: &NoteSettings) -> Option<String> {
+    let file_name = filename.file_name()?.to_str()?;
+    let captures = get_filename_captures(file_name, settings)?;
+    Some(captures.name("title")?.as_str().replace("_", " "))
 }
 
-fn get_filename_from_title(title: &str, date_time: DateTime<Local>) -> String {
+fn get_filename_captures<'a>(file_name: &'a str, settings: &NoteSettings) -> Option<Captures<'a>> {
+    let pattern = regex::escape(&settings.filename_format)
+        .replace(r"\{dateString\}", "(?P<date>.+?)")
+        .replace(r"\{titleString\}", "(?P<title>.*)")
+        .replace(r"\{extension\}", "[^.]*");
+    Regex::new(&format!("^{}$", pattern))
+        .ok()?
+        .captures(file_name)
+}
+
+fn get_filename_from_title(
+    title: &str,
+    date_time: DateTime<Local>,
+    format: NoteFormat,
+    settings: &NoteSettings,
+) -> String {
     let title_string = title.replace(" ", "_");
-    let date_string = format!("{}", date_time.format(NOTE_DATE_FORMAT_STR));
-    NOTE_FILENAME_STR_FORMAT
+    let date_string = format!("{}", date_time.format(&settings.date_format));
+    settings
+        .filename_format
         .replace("{titleString}", &title_string)
         .replace("{dateString}", &date_string)
-}
-
-fn get_title_string(title: &str) -> String {
-    format!("#+TITLE: {}", title)
+        .replace("{extension}", format.extension())
 }
 
 pub fn create_new_note_from_title(notes: &Notes, folder: &Path, title: &str) -> Result<Note> {
@@ -146,14 +208,14 @@ pub fn create_new_note_from_title(notes: &Notes, folder: &Path, title: &str) ->
         }
         None => {}
     };
-    let note = Note::from_folder_and_title(folder, title);
+    let note = Note::from_folder_and_title(folder, title, &notes.settings);
     if note.filename.exists() {
         return Err(anyhow!(
             "Note with filename {:?} already exists",
             note.filename
         ));
     }
-    note.write_without_contents()
+    note.write_without_contents(&notes.settings)
         .context("Failed to write note")?;
     Ok(note)
 }
@@ -15,9 +15,6 @@ impl NoteArg {
         if !self.filename.exists() {
             return None;
         }
-        notes
-            .iter()
-            .filter(|n| n.filename == self.filename.canonicalize().unwrap())
-            .next()
+        notes.find_by_filename(&self.filename.canonicalize().unwrap())
     }
 }
@@ -0,0 +1,417 @@
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use regex::Captures;
+use regex::Regex;
+
+use crate::config::MARKDOWN_FRONT_MATTER_DELIMITER;
+use crate::config::MARKDOWN_FRONT_MATTER_TAGS_STRING;
+use crate::config::MARKDOWN_FRONT_MATTER_TITLE_STRING;
+use crate::config::MARKDOWN_LINK_FORMAT;
+use crate::config::MARKDOWN_NOTE_EXTENSION;
+use crate::config::MARKDOWN_TITLE_STRING;
+use crate::config::NOTE_EXTENSION;
+use crate::config::ORG_FILETAGS_STRING;
+use crate::config::ORG_KEYWORD_PREFIX;
+use crate::config::ORG_TAGS_STRING;
+use crate::settings::NoteSettings;
+
+/// The file format of a note, which determines how titles and links are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoteFormat {
+    #[default]
+    Org,
+    Markdown,
+}
+
+impl NoteFormat {
+    pub fn from_filename(filename: &Path) -> Option<NoteFormat> {
+        let extension = filename.extension()?.to_str()?;
+        if extension == NOTE_EXTENSION {
+            Some(NoteFormat::Org)
+        } else if extension == MARKDOWN_NOTE_EXTENSION {
+            Some(NoteFormat::Markdown)
+        } else {
+            None
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            NoteFormat::Org => NOTE_EXTENSION,
+            NoteFormat::Markdown => MARKDOWN_NOTE_EXTENSION,
+        }
+    }
+
+    /// Whether the title and the tags can be read from the leading keyword lines alone.
+    pub fn has_header_keywords(&self) -> bool {
+        matches!(self, NoteFormat::Org)
+    }
+
+    /// The number of lines before the body of the note: The title and keyword lines of org notes,
+    /// the YAML front matter of markdown notes.
+    pub fn get_header_length(&self, contents: &str) -> usize {
+        let lines = get_lines(contents);
+        match self {
+            NoteFormat::Org => get_org_header_length(&lines),
+            NoteFormat::Markdown => get_front_matter_end(&lines).map(|end| end + 1).unwrap_or(0),
+        }
+    }
+
+    pub fn get_title(&self, contents: &str, settings: &NoteSettings) -> Result<String> {
+        let (line_number, title_line) = self
+            .find_title_line(contents)
+            .ok_or_else(|| anyhow!("Note does not contain title"))?;
+        self.get_title_from_line(title_line, settings)
+            .ok_or_else(|| {
+                anyhow!(format!(
+                    "Invalid title string in line {}: {}",
+                    line_number + 1,
+                    title_line
+                ))
+            })
+    }
+
+    pub fn get_title_string(&self, title: &str, settings: &NoteSettings) -> String {
+        match self {
+            NoteFormat::Org => format!("{}{}", settings.title_string, title),
+            NoteFormat::Markdown => format!("{}{}", MARKDOWN_TITLE_STRING, title),
+        }
+    }
+
+    /// Replace the line containing the title in the contents of a note.
+    pub fn replace_title(
+        &self,
+        contents: &str,
+        title: &str,
+        settings: &NoteSettings,
+    ) -> Result<String> {
+        let (line_number, title_line) = self
+            .find_title_line(contents)
+            .ok_or_else(|| anyhow!("Note does not contain title"))?;
+        let new_title_line = match title_line.starts_with(MARKDOWN_FRONT_MATTER_TITLE_STRING) {
+            true => format!(
+                "{}{}",
+                MARKDOWN_FRONT_MATTER_TITLE_STRING,
+                quote_yaml_string(title)
+            ),
+            false => self.get_title_string(title, settings),
+        };
+        let mut lines: Vec<&str> = contents.split('\n').collect();
+        lines[line_number] = &new_title_line;
+        Ok(lines.join("\n"))
+    }
+
+    /// Find the line containing the title and its line number. Org notes have the title in
+    /// the first line. Markdown notes either have a title entry in the YAML front matter or
+    /// a leading heading.
+    pub fn find_title_line<'a>(&self, contents: &'a str) -> Option<(usize, &'a str)> {
+        let lines = get_lines(contents);
+        let line_number = match self {
+            NoteFormat::Org => Some(0),
+            NoteFormat::Markdown => {
+                let front_matter_end = get_front_matter_end(&lines);
+                let front_matter_title = front_matter_end.and_then(|end| {
+                    (1..end).find(|i| lines[*i].starts_with(MARKDOWN_FRONT_MATTER_TITLE_STRING))
+                });
+                let body_start = front_matter_end.map(|end| end + 1).unwrap_or(0);
+                front_matter_title.or_else(|| {
+                    (body_start..lines.len())
+                        .find(|i| !lines[*i].trim().is_empty())
+                        .filter(|i| lines[*i].starts_with(MARKDOWN_TITLE_STRING))
+                })
+            }
+        }?;
+        lines.get(line_number).map(|line| (line_number, *line))
+    }
+
+    /// Read the tags of a note. Org notes have them in #+FILETAGS: or #+TAGS: lines of the header,
+    /// markdown notes in a tags entry in the YAML front matter. Only the header is read so that
+    /// the tags are the same whether the contents were read completely or up to the header.
+    pub fn get_tags(&self, contents: &str) -> Vec<String> {
+        let lines = get_lines(contents);
+        let mut tags = vec![];
+        match self {
+            NoteFormat::Org => {
+                for line in lines[..get_org_header_length(&lines)].iter() {
+                    if let Some(value) = get_org_tags_value(line) {
+                        tags.extend(split_tags(value, &[':', ' ', '\t']));
+                    }
+                }
+            }
+            NoteFormat::Markdown => {
+                if let Some((start, end)) = find_front_matter_tags(&lines) {
+                    let value = lines[start][MARKDOWN_FRONT_MATTER_TAGS_STRING.len()..].trim();
+                    match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                        Some(list) => tags.extend(split_tags(list, &[','])),
+                        None => tags.extend(split_tags(value, &[',', ' '])),
+                    }
+                    for line in lines[start + 1..end].iter() {
+                        tags.extend(split_tags(line.trim_start()[1..].trim(), &[]));
+                    }
+                }
+            }
+        }
+        let mut unique_tags: Vec<String> = vec![];
+        for tag in tags {
+            if !unique_tags.contains(&tag) {
+                unique_tags.push(tag);
+            }
+        }
+        unique_tags
+    }
+
+    /// Replace the tags of a note. For org notes, all tags are written into a single
+    /// #+FILETAGS: line below the title and any other tag lines are removed. Markdown notes
+    /// get a tags entry in the front matter, which is added if necessary.
+    pub fn set_tags(&self, contents: &str, tags: &[String]) -> String {
+        let mut lines: Vec<String> = get_lines(contents)
+            .into_iter()
+            .map(|line| line.to_owned())
+            .collect();
+        match self {
+            NoteFormat::Org => {
+                let line_refs: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+                let tag_lines: Vec<usize> = (0..get_org_header_length(&line_refs))
+                    .filter(|i| get_org_tags_value(&lines[*i]).is_some())
+                    .collect();
+                let position = tag_lines.first().copied().unwrap_or(1.min(lines.len()));
+                for i in tag_lines.iter().rev() {
+                    lines.remove(*i);
+                }
+                if !tags.is_empty() {
+                    let tags_line = format!("{}:{}:", ORG_FILETAGS_STRING, tags.join(":"));
+                    lines.insert(position, tags_line);
+                }
+            }
+            NoteFormat::Markdown => {
+                let line_refs: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+                let front_matter_end = get_front_matter_end(&line_refs);
+                let tags_lines = find_front_matter_tags(&line_refs);
+                let tags_line = format!(
+                    "{} [{}]",
+                    MARKDOWN_FRONT_MATTER_TAGS_STRING,
+                    tags.iter()
+                        .map(|tag| quote_yaml_list_item(tag))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                );
+                match (front_matter_end, tags_lines) {
+                    (_, Some((start, end))) => {
+                        lines.drain(start..end);
+                        if !tags.is_empty() {
+                            lines.insert(start, tags_line);
+                        }
+                    }
+                    (Some(end), None) => {
+                        if !tags.is_empty() {
+                            lines.insert(end, tags_line);
+                        }
+                    }
+                    (None, None) => {
+                        if !tags.is_empty() {
+                            let front_matter = [
+                                MARKDOWN_FRONT_MATTER_DELIMITER.to_owned(),
+                                tags_line,
+                                MARKDOWN_FRONT_MATTER_DELIMITER.to_owned(),
+                            ];
+                            lines.splice(0..0, front_matter);
+                        }
+                    }
+                }
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn get_title_from_line(&self, line: &str, settings: &NoteSettings) -> Option<String> {
+        let title = match self {
+            NoteFormat::Org => line.strip_prefix(&settings.title_string)?.to_owned(),
+            NoteFormat::Markdown => match line.strip_prefix(MARKDOWN_FRONT_MATTER_TITLE_STRING) {
+                Some(value) => unquote_yaml_string(value.trim()),
+                None => line.strip_prefix(MARKDOWN_TITLE_STRING)?.trim().to_owned(),
+            },
+        };
+        Some(title)
+    }
+
+    pub fn format_link(
+        &self,
+        relative_path: &Path,
+        title: &str,
+        settings: &NoteSettings,
+    ) -> String {
+        let format = match self {
+            NoteFormat::Org => settings.link_format.as_str(),
+            NoteFormat::Markdown => MARKDOWN_LINK_FORMAT,
+        };
+        format
+            .replace("{relative_path}", relative_path.to_str().unwrap())
+            .replace("{title}", title)
+    }
+
+    fn get_link_regex(&self) -> Regex {
+        match self {
+            NoteFormat::Org => {
+                Regex::new(r"\[\[file:(?P<path>.*?)\]\[(?P<description>.*?)\]\]").unwrap()
+            }
+            NoteFormat::Markdown => {
+                Regex::new(r"(?P<image>!?)\[(?P<description>[^\]]*)\]\((?P<path>[^)\s]+)\)")
+                    .unwrap()
+            }
+        }
+    }
+
+    /// Whether a match of the link regex is a link to another note. Markdown links can also
+    /// point to web pages or images, which are ignored.
+    fn is_note_link(&self, cap: &Captures) -> bool {
+        match self {
+            NoteFormat::Org => true,
+            NoteFormat::Markdown => {
+                let path = &cap["path"];
+                cap["image"].is_empty()
+                    && !path.contains("://")
+                    && NoteFormat::from_filename(Path::new(path)).is_some()
+            }
+        }
+    }
+
+    /// The byte ranges of all links in the contents, including links to web pages or images.
+    pub fn get_link_ranges(&self, contents: &str) -> Vec<Range<usize>> {
+        self.get_link_regex()
+            .find_iter(contents)
+            .map(|m| m.range())
+            .collect()
+    }
+
+    pub fn get_link_filenames(&self, contents: &str) -> Vec<PathBuf> {
+        self.get_link_regex()
+            .captures_iter(contents)
+            .filter(|cap| self.is_note_link(cap))
+            .map(|cap| Path::new(&cap["path"]).to_path_buf())
+            .collect()
+    }
+
+    /// Replace every link in the contents for which `replace` returns a new link text.
+    /// `replace` is called with the (relative) link path and the link description.
+    pub fn replace_links<F>(&self, contents: &str, mut replace: F) -> String
+    where
+        F: FnMut(&Path, &str) -> Option<String>,
+    {
+        self.get_link_regex()
+            .replace_all(contents, |cap: &Captures| {
+                if !self.is_note_link(cap) {
+                    return cap[0].to_string();
+                }
+                replace(Path::new(&cap["path"]), &cap["description"])
+                    .unwrap_or_else(|| cap[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+fn get_lines(contents: &str) -> Vec<&str> {
+    contents
+        .split('\n')
+        .map(|line| line.trim_end_matches('\r'))
+        .collect()
+}
+
+/// The index of the line closing the YAML front matter, if the note has one.
+fn get_front_matter_end(lines: &[&str]) -> Option<usize> {
+    if lines.first() != Some(&MARKDOWN_FRONT_MATTER_DELIMITER) {
+        return None;
+    }
+    (1..lines.len()).find(|i| lines[*i] == MARKDOWN_FRONT_MATTER_DELIMITER)
+}
+
+/// The range of lines of the tags entry in the front matter, including
+/// the list items below it.
+fn find_front_matter_tags(lines: &[&str]) -> Option<(usize, usize)> {
+    let front_matter_end = get_front_matter_end(lines)?;
+    let start =
+        (1..front_matter_end).find(|i| lines[*i].starts_with(MARKDOWN_FRONT_MATTER_TAGS_STRING))?;
+    let end = (start + 1..front_matter_end)
+        .find(|i| !lines[*i].trim_start().starts_with('-'))
+        .unwrap_or(front_matter_end);
+    Some((start, end))
+}
+
+/// The number of lines of the org header: The title line and all directly following keyword lines.
+fn get_org_header_length(lines: &[&str]) -> usize {
+    let keyword_lines = lines
+        .iter()
+        .skip(1)
+        .take_while(|line| line.starts_with(ORG_KEYWORD_PREFIX))
+        .count();
+    lines.len().min(1 + keyword_lines)
+}
+
+fn get_org_tags_value(line: &str) -> Option<&str> {
+    [ORG_FILETAGS_STRING, ORG_TAGS_STRING]
+        .iter()
+        .map(|keyword| keyword.trim_end())
+        .find(|keyword| {
+            line.get(..keyword.len())
+                .map(|start| start.eq_ignore_ascii_case(keyword))
+                .unwrap_or(false)
+        })
+        .map(|keyword| &line[keyword.len()..])
+}
+
+/// Split a list of tags at the separators, except within quotes.
+fn split_tags(value: &str, separators: &[char]) -> Vec<String> {
+    let mut quote = None;
+    let mut parts = vec![];
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if (c == '\'' || c == '"') && value[start..i].trim().is_empty() => quote = Some(c),
+            None if separators.contains(&c) => {
+                parts.push(&value[start..i]);
+                start = i + c.len_utf8();
+            }
+            None => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+        .into_iter()
+        .map(|tag| unquote_yaml_string(tag.trim()))
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+fn quote_yaml_list_item(value: &str) -> String {
+    match value.contains(',') || value.contains(']') {
+        true => format!("'{}'", value.replace('\'', "''")),
+        false => quote_yaml_string(value),
+    }
+}
+
+fn unquote_yaml_string(value: &str) -> String {
+    let is_quoted_with =
+        |quote| value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote);
+    if is_quoted_with('\'') {
+        value[1..value.len() - 1].replace("''", "'")
+    } else if is_quoted_with('"') {
+        value[1..value.len() - 1].replace("\\\"", "\"")
+    } else {
+        value.to_owned()
+    }
+}
+
+fn quote_yaml_string(value: &str) -> String {
+    let needs_quotes = value.contains(": ")
+        || value.contains(" #")
+        || value.starts_with(|c: char| "\"'#&*!|>%@`[]{},-?".contains(c));
+    match needs_quotes {
+        true => format!("'{}'", value.replace('\'', "''")),
+        false => value.to_owned(),
+    }
+}
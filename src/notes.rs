@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -12,16 +13,21 @@ use generational_arena::Index;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::config::NOTE_EXTENSION;
 use crate::dir_utils::get_files;
 use crate::dir_utils::get_files_recursively;
-use crate::note::get_link_filenames;
 use crate::note::Note;
+use crate::note_format::NoteFormat;
+use crate::notes_sqlite::is_sqlite_database;
+use crate::notes_sqlite::read_notes_from_sqlite_database;
+use crate::settings::NoteSettings;
 
 #[derive(Deserialize, Serialize)]
 pub struct NotesDatabase {
     notes: Notes,
-    modified_timestamp: Option<SystemTime>,
+    modified_times: HashMap<PathBuf, SystemTime>,
+    /// The title string the titles were read with
+    #[serde(default)]
+    title_string: String,
 }
 
 impl NotesDatabase {
@@ -41,7 +47,12 @@ impl NotesDatabase {
 pub struct Notes {
     arena: Arena<Note>,
     pub folder: PathBuf,
-    // map: HashMap<PathBuf, Index>,
+    #[serde(skip)]
+    filename_map: HashMap<PathBuf, Index>,
+    #[serde(skip)]
+    title_map: HashMap<String, Index>,
+    #[serde(skip)]
+    pub settings: NoteSettings,
 }
 
 impl Notes {
@@ -62,40 +73,58 @@ impl Notes {
     }
 
     pub fn find_by_filename(&self, filename: &Path) -> Option<&Note> {
-        self.iter().find(|n| n.filename == filename)
+        self.find_index_by_filename(filename)
+            .map(|index| &self.arena[index])
     }
 
     pub fn find_by_title(&self, title: &str) -> Option<&Note> {
-        self.iter().find(|n| n.title == title)
+        self.find_index_by_title(title)
+            .map(|index| &self.arena[index])
     }
 
     pub fn find_index_by_filename(&self, filename: &Path) -> Option<Index> {
-        self.index_iter()
-            .find(|(_, n)| n.filename == filename)
-            .map(|(i, _)| i)
+        self.filename_map.get(filename).copied()
     }
 
     pub fn find_index_by_title(&self, title: &str) -> Option<Index> {
-        self.index_iter()
-            .find(|(_, n)| n.title == title)
-            .map(|(i, _)| i)
+        self.title_map.get(title).copied()
     }
 
-    pub fn empty(folder: PathBuf) -> Notes {
-        Notes {
-            arena: Arena::new(),
-            folder,
-        }
+    pub fn empty(folder: PathBuf, settings: &NoteSettings) -> Notes {
+        Notes::from_arena(folder, Arena::new(), settings)
     }
 
-    pub fn from_arena_and_map(folder: PathBuf, arena: Arena<Note>) -> Result<Notes> {
-        Ok(Notes { folder, arena })
+    pub fn from_arena(folder: PathBuf, arena: Arena<Note>, settings: &NoteSettings) -> Notes {
+        let mut notes = Notes {
+            folder,
+            arena,
+            filename_map: HashMap::new(),
+            title_map: HashMap::new(),
+            settings: settings.clone(),
+        };
+        notes.build_maps();
+        notes
     }
 
     pub fn push(&mut self, note: Note) -> Index {
+        let filename = note.filename.clone();
+        let title = note.title.clone();
         let idx = self.arena.insert(note);
+        self.filename_map.insert(filename, idx);
+        self.title_map.entry(title).or_insert(idx);
         idx
     }
+
+    /// Rebuild the lookup maps from filename and title to the notes.
+    /// If multiple notes have the same title, the first one is found.
+    fn build_maps(&mut self) {
+        self.filename_map.clear();
+        self.title_map.clear();
+        for (index, note) in self.arena.iter() {
+            self.filename_map.insert(note.filename.clone(), index);
+            self.title_map.entry(note.title.clone()).or_insert(index);
+        }
+    }
 }
 
 pub fn get_filename_map(arena: &Arena<Note>) -> Result<HashMap<PathBuf, Index>> {
@@ -123,66 +152,116 @@ pub fn read_notes_from_database(
     note_folder: &Path,
     db_path: &Path,
     multidir: bool,
+    settings: &NoteSettings,
 ) -> Result<Notes> {
     let mb_notes_db = NotesDatabase::from_file(db_path);
     let mut notes_db = match mb_notes_db {
-        Err(_) => NotesDatabase {
-            modified_timestamp: None,
-            notes: Notes::empty(note_folder.to_path_buf()),
+        Ok(notes_db)
+            if notes_db.notes.folder == note_folder
+                && notes_db.title_string == settings.title_string =>
+        {
+            notes_db
+        }
+        _ => NotesDatabase {
+            modified_times: HashMap::new(),
+            notes: Notes::empty(note_folder.to_path_buf(), settings),
+            title_string: settings.title_string.clone(),
         },
-        Ok(notes_db) => notes_db,
     };
+    notes_db.notes.settings = settings.clone();
     update_database_from_db_file(note_folder, &mut notes_db, multidir)?;
     notes_db.to_file(db_path)?;
     Ok(notes_db.notes)
 }
 
-fn filter_result<F, T>(it: Box<dyn Iterator<Item = T>>, predicate: F) -> Result<Vec<T>>
-where
-    F: Fn(&T) -> Result<bool>,
-{
-    let results: Result<Vec<(T, bool)>> = it
-        .map(move |t| match predicate(&t) {
-            Err(e) => Err(e),
-            Ok(v) => Ok((t, v)),
-        })
-        .collect();
-    Ok(results?
-        .into_iter()
-        .filter(|(_, v)| *v)
-        .map(|(t, _)| t)
-        .collect())
-}
-
-fn get_newer_files_maybe_recursively(
-    note_folder: &Path,
-    notes_db: &mut NotesDatabase,
-    multidir: bool,
-) -> Result<Vec<PathBuf>> {
-    let files = get_files_maybe_recursively(note_folder, multidir)?;
-    filter_result(Box::new(files.into_iter()), |path| {
-        Ok(match notes_db.modified_timestamp {
-            Some(db_timestamp) => path.metadata()?.modified()? > db_timestamp,
-            None => true,
-        })
-    })
-}
-
+/// Bring the database up to date with the note files: Remove notes whose files vanished,
+/// re-parse files that changed since the last run, add new files and
+/// rebuild the links of all affected notes.
 fn update_database_from_db_file(
     note_folder: &Path,
     notes_db: &mut NotesDatabase,
     multidir: bool,
 ) -> Result<()> {
-    for file in get_newer_files_maybe_recursively(note_folder, notes_db, multidir)? {
-        dbg!(&file);
+    let files = get_note_files(note_folder, multidir)?;
+    let settings = &notes_db.notes.settings;
+    let arena = &mut notes_db.notes.arena;
+    let mut affected = remove_vanished_notes(arena, &files);
+    let map = get_filename_map(arena)?;
+    let mut modified_times = HashMap::new();
+    for file in files {
+        // Read the modification time before reading the file so that files
+        // which are modified while this runs are read again the next time.
+        let modified_time = file.metadata()?.modified()?;
+        let is_modified = notes_db.modified_times.get(&file) != Some(&modified_time);
+        modified_times.insert(file.clone(), modified_time);
+        match map.get(&file) {
+            Some(index) => {
+                if is_modified {
+                    let note = Note::from_filename_no_links(&file, settings)?;
+                    arena[*index].title = note.title;
+                    arena[*index].tags = note.tags;
+                    affected.insert(*index);
+                }
+            }
+            None => {
+                affected.insert(arena.insert(Note::from_filename_no_links(&file, settings)?));
+            }
+        }
     }
+    let map = get_filename_map(arena)?;
+    for index in affected {
+        clear_links(arena, index);
+        set_links(arena, &map, index)?;
+    }
+    notes_db.notes.build_maps();
+    notes_db.modified_times = modified_times;
     Ok(())
 }
 
-pub fn read_notes(note_folder: &Path, database: &Option<PathBuf>, multidir: bool) -> Result<Notes> {
+/// Remove all notes whose files do not exist anymore. Returns the notes that linked to the removed notes.
+fn remove_vanished_notes(arena: &mut Arena<Note>, files: &[PathBuf]) -> HashSet<Index> {
+    let files: HashSet<&PathBuf> = files.iter().collect();
+    let vanished: Vec<Index> = arena
+        .iter()
+        .filter(|(_, note)| !files.contains(&note.filename))
+        .map(|(index, _)| index)
+        .collect();
+    let mut affected = HashSet::new();
+    for index in vanished.iter() {
+        let note = arena.remove(*index).unwrap();
+        for link in note.links {
+            if let Some(target) = arena.get_mut(link) {
+                target.backlinks.retain(|backlink| backlink != index);
+            }
+        }
+        affected.extend(note.backlinks);
+    }
+    affected.retain(|index| arena.contains(*index));
+    affected
+}
+
+/// Remove the outgoing links of a note and the corresponding backlinks.
+fn clear_links(arena: &mut Arena<Note>, index: Index) {
+    let links = std::mem::take(&mut arena[index].links);
+    for link in links {
+        if let Some(target) = arena.get_mut(link) {
+            target.backlinks.retain(|backlink| *backlink != index);
+        }
+    }
+}
+
+pub fn read_notes(
+    note_folder: &Path,
+    database: &Option<PathBuf>,
+    multidir: bool,
+    settings: &NoteSettings,
+) -> Result<Notes> {
     match database {
-        None => read_notes_from_folder(note_folder, multidir),
-        Some(db_path) => read_notes_from_database(note_folder, db_path, multidir),
+        None => read_notes_from_folder(note_folder, multidir, settings),
+        Some(db_path) => match is_sqlite_database(db_path) {
+            true => read_notes_from_sqlite_database(note_folder, db_path, multidir, settings),
+            false => read_notes_from_database(note_folder, db_path, multidir, settings),
+        },
     }
 }
 
@@ -193,22 +272,36 @@ fn get_files_maybe_recursively(note_folder: &Path, recursive: bool) -> Result<Ve
     }
 }
 
-pub fn read_notes_from_folder(note_folder: &Path, multidir: bool) -> Result<Notes> {
-    let mut arena = Arena::new();
-    let mut indices = vec![];
+pub fn get_note_files(note_folder: &Path, multidir: bool) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
     for file in get_files_maybe_recursively(note_folder, multidir)? {
-        if let Some(extension) = file.extension() {
-            if extension == NOTE_EXTENSION {
-                let note = Note::from_filename_no_links(&file.canonicalize()?)?;
-                indices.push(arena.insert(note));
-            }
+        if NoteFormat::from_filename(&file).is_some() {
+            files.push(file.canonicalize()?);
         }
     }
+    Ok(files)
+}
+
+pub fn read_notes_from_folder(
+    note_folder: &Path,
+    multidir: bool,
+    settings: &NoteSettings,
+) -> Result<Notes> {
+    let mut arena = Arena::new();
+    let mut indices = vec![];
+    for file in get_note_files(note_folder, multidir)? {
+        let note = Note::from_filename_no_links(&file, settings)?;
+        indices.push(arena.insert(note));
+    }
     let map = get_filename_map(&arena)?;
     for i in indices {
         set_links(&mut arena, &map, i)?;
     }
-    Notes::from_arena_and_map(note_folder.to_path_buf(), arena)
+    Ok(Notes::from_arena(
+        note_folder.to_path_buf(),
+        arena,
+        settings,
+    ))
 }
 
 pub fn set_links(
@@ -222,13 +315,8 @@ pub fn set_links(
         .parent()
         .expect(&format!("Invalid filename for note: {:?}", &note.filename));
     let contents = note.get_contents().context("While reading note contents")?;
-    for relative_link in get_link_filenames(&contents) {
-        let link_res = parent_dir.join(&relative_link).canonicalize();
-        let link = link_res.context(format!(
-            "Invalid link in note {:?} : {:?}",
-            &cloned_filename,
-            &(relative_link.clone())
-        ))?;
+    for relative_link in note.format().get_link_filenames(&contents) {
+        let link = resolve_link(parent_dir, &relative_link, &cloned_filename)?;
         let i = map
             .get(&link)
             .ok_or_else(|| anyhow!(format!("Invalid link in file: {}", link.to_str().unwrap())))?;
@@ -246,3 +334,14 @@ pub fn set_links(
     }
     Ok(())
 }
+
+/// Get the absolute path of the file a relative link in a note in `parent_dir` points to.
+pub fn resolve_link(parent_dir: &Path, relative_link: &Path, filename: &Path) -> Result<PathBuf> {
+    parent_dir
+        .join(relative_link)
+        .canonicalize()
+        .context(format!(
+            "Invalid link in note {:?} : {:?}",
+            filename, relative_link
+        ))
+}
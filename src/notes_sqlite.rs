@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use generational_arena::Arena;
+use rusqlite::params;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+use rusqlite::NO_PARAMS;
+
+use crate::anki::close_connection;
+use crate::note::Note;
+use crate::note_format::NoteFormat;
+use crate::notes::get_note_files;
+use crate::notes::resolve_link;
+use crate::notes::Notes;
+use crate::settings::NoteSettings;
+
+static SQLITE_EXTENSIONS: &[&str] = &["sqlite", "sqlite3", "db"];
+
+/// Increased whenever the tables change, so that databases written by older versions are rebuilt.
+static SCHEMA_VERSION: &str = "3";
+
+static CREATE_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS notes (
+    id INTEGER PRIMARY KEY,
+    filename TEXT NOT NULL UNIQUE,
+    title TEXT NOT NULL,
+    mtime INTEGER NOT NULL,
+    hash TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS links (
+    source INTEGER NOT NULL,
+    target INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS links_source ON links (source);
+CREATE INDEX IF NOT EXISTS links_target ON links (target);
+CREATE TABLE IF NOT EXISTS tags (
+    note INTEGER NOT NULL,
+    tag TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS tags_note ON tags (note);
+";
+
+static DROP_TABLES: &str = "
+DROP TABLE IF EXISTS tags;
+DROP TABLE IF EXISTS links;
+DROP TABLE IF EXISTS notes;
+";
+
+type NoteId = i64;
+
+struct FileInfo {
+    id: NoteId,
+    mtime: i64,
+    hash: String,
+}
+
+pub fn is_sqlite_database(db_path: &Path) -> bool {
+    db_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| SQLITE_EXTENSIONS.contains(&extension))
+        .unwrap_or(false)
+}
+
+/// Read the notes from an SQLite database, after updating the rows of all note files
+/// that changed since the last run. Only changed files are parsed again, but all notes, links
+/// and tags are still loaded into memory, so lookups by title, filename or backlinks do not
+/// query the database.
+pub fn read_notes_from_sqlite_database(
+    note_folder: &Path,
+    db_path: &Path,
+    multidir: bool,
+    settings: &NoteSettings,
+) -> Result<Notes> {
+    let mut connection = Connection::open(db_path).context("While opening pundit database")?;
+    connection
+        .execute_batch(CREATE_TABLES)
+        .context("While creating pundit database tables")?;
+    let transaction = connection.transaction()?;
+    reset_if_outdated(&transaction, note_folder, settings)?;
+    update_database(&transaction, note_folder, multidir, settings)?;
+    transaction.commit()?;
+    let notes = read_notes_from_tables(&connection, note_folder, settings)?;
+    close_connection(connection)?;
+    Ok(notes)
+}
+
+/// Recreate the tables if the database was written for another folder, by a version of pundit with
+/// different tables or with another title string.
+fn reset_if_outdated(
+    connection: &Connection,
+    note_folder: &Path,
+    settings: &NoteSettings,
+) -> Result<()> {
+    let folder = note_folder.to_str().unwrap();
+    if get_meta(connection, "folder")?.as_deref() != Some(folder)
+        || get_meta(connection, "version")?.as_deref() != Some(SCHEMA_VERSION)
+        || get_meta(connection, "title_string")?.as_deref() != Some(&settings.title_string)
+    {
+        connection.execute_batch(DROP_TABLES)?;
+        connection.execute_batch(CREATE_TABLES)?;
+        set_meta(connection, "folder", folder)?;
+        set_meta(connection, "version", SCHEMA_VERSION)?;
+        set_meta(connection, "title_string", &settings.title_string)?;
+    }
+    Ok(())
+}
+
+fn get_meta(connection: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(connection
+        .query_row(
+            "SELECT value FROM meta WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+fn set_meta(connection: &Connection, key: &str, value: &str) -> Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+fn update_database(
+    connection: &Connection,
+    note_folder: &Path,
+    multidir: bool,
+    settings: &NoteSettings,
+) -> Result<()> {
+    let files = get_note_files(note_folder, multidir)?;
+    let stored = read_file_infos(connection)?;
+    let mut affected = remove_vanished_notes(connection, &files, &stored)?;
+    for file in files.iter() {
+        let mtime = get_mtime(file)?;
+        match stored.get(file) {
+            Some(info) if info.mtime == mtime => {}
+            Some(info) => {
+                let contents = read_contents(file)?;
+                let hash = get_hash(&contents);
+                if hash == info.hash {
+                    connection.execute(
+                        "UPDATE notes SET mtime = ?1 WHERE id = ?2",
+                        params![mtime, info.id],
+                    )?;
+                } else {
+                    let note = Note::from_contents(file, &contents, settings)?;
+                    connection.execute(
+                        "UPDATE notes SET (title, mtime, hash) = (?1, ?2, ?3) WHERE id = ?4",
+                        params![note.title, mtime, hash, info.id],
+                    )?;
+                    update_tags(connection, info.id, &note.tags)?;
+                    affected.insert(info.id);
+                }
+            }
+            None => {
+                let contents = read_contents(file)?;
+                let note = Note::from_contents(file, &contents, settings)?;
+                connection.execute(
+                    "INSERT INTO notes (filename, title, mtime, hash) VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        file.to_str().unwrap(),
+                        note.title,
+                        mtime,
+                        get_hash(&contents)
+                    ],
+                )?;
+                let id = connection.last_insert_rowid();
+                update_tags(connection, id, &note.tags)?;
+                affected.insert(id);
+            }
+        }
+    }
+    for id in affected {
+        update_links(connection, id)?;
+    }
+    Ok(())
+}
+
+fn read_file_infos(connection: &Connection) -> Result<HashMap<PathBuf, FileInfo>> {
+    let mut stmt = connection.prepare("SELECT id, filename, mtime, hash FROM notes")?;
+    let rows = stmt.query_map(NO_PARAMS, |row| {
+        let filename: String = row.get(1)?;
+        Ok((
+            PathBuf::from(filename),
+            FileInfo {
+                id: row.get(0)?,
+                mtime: row.get(2)?,
+                hash: row.get(3)?,
+            },
+        ))
+    })?;
+    rows.map(|row| row.context("Reading row in notes table"))
+        .collect()
+}
+
+/// Remove the rows of all notes whose files do not exist anymore. Returns the notes that linked to the removed notes.
+fn remove_vanished_notes(
+    connection: &Connection,
+    files: &[PathBuf],
+    stored: &HashMap<PathBuf, FileInfo>,
+) -> Result<HashSet<NoteId>> {
+    let files: HashSet<&PathBuf> = files.iter().collect();
+    let mut affected = HashSet::new();
+    let mut removed = vec![];
+    for (filename, info) in stored.iter() {
+        if files.contains(filename) {
+            continue;
+        }
+        affected.extend(get_backlink_ids(connection, info.id)?);
+        connection.execute(
+            "DELETE FROM links WHERE source = ?1 OR target = ?1",
+            params![info.id],
+        )?;
+        connection.execute("DELETE FROM tags WHERE note = ?1", params![info.id])?;
+        connection.execute("DELETE FROM notes WHERE id = ?1", params![info.id])?;
+        removed.push(info.id);
+    }
+    for id in removed {
+        affected.remove(&id);
+    }
+    Ok(affected)
+}
+
+fn get_backlink_ids(connection: &Connection, id: NoteId) -> Result<Vec<NoteId>> {
+    let mut stmt = connection.prepare("SELECT source FROM links WHERE target = ?1")?;
+    let rows = stmt.query_map(params![id], |row| row.get(0))?;
+    rows.map(|row| row.context("Reading row in links table"))
+        .collect()
+}
+
+fn update_tags(connection: &Connection, id: NoteId, tags: &[String]) -> Result<()> {
+    connection.execute("DELETE FROM tags WHERE note = ?1", params![id])?;
+    for tag in tags {
+        connection.execute(
+            "INSERT INTO tags (note, tag) VALUES (?1, ?2)",
+            params![id, tag],
+        )?;
+    }
+    Ok(())
+}
+
+/// Replace the rows of all outgoing links of a note by the links currently in its file.
+fn update_links(connection: &Connection, id: NoteId) -> Result<()> {
+    let filename: String = connection.query_row(
+        "SELECT filename FROM notes WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )?;
+    let filename = PathBuf::from(filename);
+    let parent_dir = filename.parent().unwrap();
+    connection.execute("DELETE FROM links WHERE source = ?1", params![id])?;
+    let format = NoteFormat::from_filename(&filename).unwrap_or_default();
+    for relative_link in format.get_link_filenames(&read_contents(&filename)?) {
+        let link = resolve_link(parent_dir, &relative_link, &filename)?;
+        let target: NoteId = connection
+            .query_row(
+                "SELECT id FROM notes WHERE filename = ?1",
+                params![link.to_str().unwrap()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow!(format!("Invalid link in file: {}", link.to_str().unwrap())))
+            .context(format!("While reading links of note {:?}", filename))?;
+        if target == id {
+            return Err(anyhow!(format!("Note links to itself: {:?}", filename)));
+        }
+        connection.execute(
+            "INSERT INTO links (source, target) VALUES (?1, ?2)",
+            params![id, target],
+        )?;
+    }
+    Ok(())
+}
+
+fn read_notes_from_tables(
+    connection: &Connection,
+    note_folder: &Path,
+    settings: &NoteSettings,
+) -> Result<Notes> {
+    let mut arena = Arena::new();
+    let mut indices = HashMap::new();
+    let mut stmt = connection.prepare("SELECT id, filename, title FROM notes ORDER BY id")?;
+    let rows = stmt.query_map(NO_PARAMS, |row| {
+        let id: NoteId = row.get(0)?;
+        let filename: String = row.get(1)?;
+        Ok((
+            id,
+            Note {
+                filename: PathBuf::from(filename),
+                title: row.get(2)?,
+                links: vec![],
+                backlinks: vec![],
+                tags: vec![],
+            },
+        ))
+    })?;
+    for row in rows {
+        let (id, note) = row.context("Reading row in notes table")?;
+        indices.insert(id, arena.insert(note));
+    }
+    let mut stmt = connection.prepare("SELECT source, target FROM links ORDER BY rowid")?;
+    let rows = stmt.query_map(NO_PARAMS, |row| {
+        Ok((row.get::<_, NoteId>(0)?, row.get::<_, NoteId>(1)?))
+    })?;
+    for row in rows {
+        let (source, target) = row.context("Reading row in links table")?;
+        let source = indices[&source];
+        let target = indices[&target];
+        arena[source].links.push(target);
+        arena[target].backlinks.push(source);
+    }
+    let mut stmt = connection.prepare("SELECT note, tag FROM tags ORDER BY rowid")?;
+    let rows = stmt.query_map(NO_PARAMS, |row| {
+        Ok((row.get::<_, NoteId>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in rows {
+        let (note, tag) = row.context("Reading row in tags table")?;
+        arena[indices[&note]].tags.push(tag);
+    }
+    Ok(Notes::from_arena(
+        note_folder.to_path_buf(),
+        arena,
+        settings,
+    ))
+}
+
+fn get_mtime(file: &Path) -> Result<i64> {
+    let modified = file.metadata()?.modified()?;
+    Ok(modified.duration_since(UNIX_EPOCH)?.as_nanos() as i64)
+}
+
+/// The SHA-1 hash of the contents, which recognizes files that were touched without changing them.
+fn get_hash(contents: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.input_str(contents);
+    hasher.result_str()
+}
+
+fn read_contents(file: &Path) -> Result<String> {
+    fs::read_to_string(file).context(format!("Reading contents of {:?}", file))
+}
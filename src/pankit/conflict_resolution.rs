@@ -0,0 +1,415 @@
+use std::env;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use log::error;
+use log::info;
+use rand::Rng;
+
+use super::get_managed_anki_tags;
+use super::get_tag_set;
+use super::join_tags;
+use crate::anki::anki_collection::AnkiCollection;
+use crate::anki::anki_model::AnkiModel;
+use crate::anki::anki_note::AnkiNote;
+use crate::anki::is_note_id_field;
+use crate::anki::ANKI_FIELD_SEPARATOR;
+use crate::note::Note;
+
+static FIELD_HEADER: &str = "@@@ ";
+static CONFLICT_START: &str = "<<<<<<< pundit";
+static CONFLICT_SEPARATOR: &str = "=======";
+static CONFLICT_END: &str = ">>>>>>> anki";
+
+/// Differing contents of the same note in anki and pundit.
+#[derive(Debug)]
+pub struct NoteConflict<'a> {
+    pub anki: &'a AnkiNote,
+    pub pundit: &'a AnkiNote,
+    pub pundit_note: &'a Note,
+}
+
+pub enum Resolution {
+    Pundit,
+    Anki,
+    /// The values of all fields of the model, in order
+    Merged(Vec<String>),
+    Skip,
+}
+
+impl<'a> NoteConflict<'a> {
+    pub fn show(&self, collection: &AnkiCollection) {
+        info!(
+            "Conflict between note contents for id {} in {}:",
+            self.anki.id,
+            self.pundit_note.filename.to_str().unwrap()
+        );
+        let field_names = self.get_field_names(collection);
+        for (i, (pundit, anki)) in self.get_field_values().into_iter().enumerate() {
+            let name = field_names
+                .as_ref()
+                .map(|names| names[i].clone())
+                .unwrap_or_else(|| format!("Field {}", i + 1));
+            if pundit == anki {
+                info!("  {}: {}", name, pundit);
+            } else {
+                info!("  {}:", name);
+                for line in pundit.lines() {
+                    info!("    pundit | {}", line);
+                }
+                for line in anki.lines() {
+                    info!("    anki   | {}", line);
+                }
+            }
+        }
+        let pundit_tags = get_tag_set(&self.pundit.tags);
+        let anki_tags = get_managed_anki_tags(self.anki, self.pundit, &[]);
+        if pundit_tags != anki_tags {
+            info!("  tags:");
+            info!("    pundit | {}", join_tags(pundit_tags));
+            info!("    anki   | {}", join_tags(anki_tags));
+        }
+    }
+
+    /// Show the conflict and ask which side to use until a valid answer is given.
+    pub fn resolve_interactively(&self, collection: &AnkiCollection) -> Result<Resolution> {
+        self.show(collection);
+        loop {
+            let answer =
+                ask("Use [p]undit, [a]nki, choose per [f]ield, [e]dit merged version or [s]kip?")?;
+            let resolution = match answer.as_str() {
+                "p" => Ok(Resolution::Pundit),
+                "a" => Ok(Resolution::Anki),
+                "s" => Ok(Resolution::Skip),
+                "f" => self.resolve_per_field(collection),
+                "e" => self.resolve_in_editor(collection),
+                _ => Err(anyhow!("Invalid answer: {}", answer)),
+            };
+            match resolution {
+                Ok(resolution) => return Ok(resolution),
+                Err(e) => error!("{:#}", e),
+            }
+        }
+    }
+
+    fn resolve_per_field(&self, collection: &AnkiCollection) -> Result<Resolution> {
+        let model = self.get_model(collection)?;
+        let mut values = vec![];
+        for (field, (pundit, anki)) in model.flds.iter().zip(self.get_field_values()) {
+            if pundit == anki || is_note_id_field(&field.name) {
+                values.push(pundit.to_owned());
+                continue;
+            }
+            loop {
+                match ask(&format!("{}: Use [p]undit or [a]nki?", field.name))?.as_str() {
+                    "p" => values.push(pundit.to_owned()),
+                    "a" => values.push(anki.to_owned()),
+                    _ => continue,
+                }
+                break;
+            }
+        }
+        Ok(self.get_resolution_for_values(values))
+    }
+
+    fn resolve_in_editor(&self, collection: &AnkiCollection) -> Result<Resolution> {
+        let model = self.get_model(collection)?;
+        let filename = create_merge_file(self.anki.id, &self.get_merged_text(model))?;
+        run_editor(filename.to_str().unwrap())?;
+        let text =
+            fs::read_to_string(&filename).context(format!("While reading {:?}", filename))?;
+        fs::remove_file(&filename).context(format!("While removing {:?}", filename))?;
+        let values = self.read_merged_text(model, &text)?;
+        Ok(self.get_resolution_for_values(values))
+    }
+
+    /// The fields of the note, each following a header line with its name. Differing values
+    /// are shown with conflict markers.
+    fn get_merged_text(&self, model: &AnkiModel) -> String {
+        let mut lines = vec![];
+        for (field, (pundit, anki)) in model.flds.iter().zip(self.get_field_values()) {
+            if is_note_id_field(&field.name) {
+                continue;
+            }
+            lines.push(format!("{}{}", FIELD_HEADER, field.name));
+            if pundit == anki {
+                lines.push(pundit.to_owned());
+            } else {
+                lines.extend(
+                    vec![
+                        CONFLICT_START,
+                        pundit,
+                        CONFLICT_SEPARATOR,
+                        anki,
+                        CONFLICT_END,
+                    ]
+                    .into_iter()
+                    .map(|line| line.to_owned()),
+                );
+            }
+        }
+        lines.join("\n") + "\n"
+    }
+
+    fn read_merged_text(&self, model: &AnkiModel, text: &str) -> Result<Vec<String>> {
+        let mut edited: Vec<(String, Vec<&str>)> = vec![];
+        for line in text.lines() {
+            if let Some(name) = line.strip_prefix(FIELD_HEADER) {
+                edited.push((name.trim().to_owned(), vec![]));
+            } else if [CONFLICT_START, CONFLICT_SEPARATOR, CONFLICT_END]
+                .iter()
+                .any(|marker| line.starts_with(marker))
+            {
+                return Err(anyhow!(
+                    "Unresolved conflict markers left in merged version"
+                ));
+            } else {
+                edited
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("Text before the first field header in merged version"))?
+                    .1
+                    .push(line);
+            }
+        }
+        model
+            .flds
+            .iter()
+            .zip(self.get_field_values())
+            .map(|(field, (pundit, _))| {
+                if is_note_id_field(&field.name) {
+                    return Ok(pundit.to_owned());
+                }
+                edited
+                    .iter()
+                    .find(|(name, _)| name == &field.name)
+                    .map(|(_, lines)| lines.join("\n"))
+                    .ok_or_else(|| anyhow!("Field {} missing in merged version", field.name))
+            })
+            .collect()
+    }
+
+    fn get_resolution_for_values(&self, values: Vec<String>) -> Resolution {
+        let values_equal = |note: &AnkiNote| {
+            values
+                .iter()
+                .map(|value| value.as_str())
+                .eq(note.flds.split(ANKI_FIELD_SEPARATOR))
+        };
+        if values_equal(self.pundit) {
+            Resolution::Pundit
+        } else if values_equal(self.anki) {
+            Resolution::Anki
+        } else {
+            Resolution::Merged(values)
+        }
+    }
+
+    fn get_field_values(&self) -> Vec<(&str, &str)> {
+        self.pundit
+            .flds
+            .split(ANKI_FIELD_SEPARATOR)
+            .zip(self.anki.flds.split(ANKI_FIELD_SEPARATOR))
+            .collect()
+    }
+
+    fn get_field_names(&self, collection: &AnkiCollection) -> Option<Vec<String>> {
+        self.get_model(collection)
+            .ok()
+            .map(|model| model.flds.iter().map(|field| field.name.clone()).collect())
+    }
+
+    /// The model of the note, if it is the same in anki and pundit.
+    fn get_model<'b>(&self, collection: &'b AnkiCollection) -> Result<&'b AnkiModel> {
+        if self.anki.mid != self.pundit.mid {
+            return Err(anyhow!(
+                "The model of note {} differs between anki and pundit",
+                self.anki.id
+            ));
+        }
+        collection
+            .models
+            .iter()
+            .find(|model| model.id == self.anki.mid)
+            .ok_or_else(|| anyhow!("No model with id {} in collection", self.anki.mid))
+    }
+}
+
+/// Write the contents into a new file in the temporary folder. The name has a random suffix, so
+/// that it cannot be guessed in advance and does not clash with the files of other runs.
+fn create_merge_file(id: i64, contents: &str) -> Result<PathBuf> {
+    let mut rng = rand::thread_rng();
+    loop {
+        let filename = env::temp_dir().join(format!(
+            "pankit-conflict-{}-{:016x}.txt",
+            id,
+            rng.gen::<u64>()
+        ));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&filename)
+        {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes())
+                    .context(format!("While writing {:?}", filename))?;
+                return Ok(filename);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err).context(format!("While creating {:?}", filename)),
+        }
+    }
+}
+
+fn ask(question: &str) -> Result<String> {
+    info!("{}", question);
+    let mut answer = String::new();
+    let num_bytes = io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("While reading answer")?;
+    if num_bytes == 0 {
+        return Err(anyhow!("No answer given"));
+    }
+    Ok(answer.trim().to_lowercase())
+}
+
+fn run_editor(filename: &str) -> Result<()> {
+    let editor = env::var("EDITOR").unwrap_or_else(|_| "vi".to_owned());
+    let mut words = editor.split_whitespace();
+    let command = words.next().ok_or_else(|| anyhow!("EDITOR is empty"))?;
+    let status = Command::new(command)
+        .args(words)
+        .arg(filename)
+        .status()
+        .context(format!("While running editor {}", editor))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(anyhow!("Editor {} exited with an error", editor)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::anki::anki_model::AnkiField;
+
+    fn get_anki_note(flds: &[&str]) -> AnkiNote {
+        AnkiNote {
+            id: 1,
+            guid: "1".to_owned(),
+            mid: 1,
+            mod_: 0,
+            usn: -1,
+            tags: "".to_owned(),
+            flds: flds.join(ANKI_FIELD_SEPARATOR),
+            sfld: flds[0].to_owned(),
+            csum: 0,
+            flags: 0,
+            data: "".to_owned(),
+        }
+    }
+
+    fn get_model() -> AnkiModel {
+        AnkiModel {
+            flds: ["Spanish", "English"]
+                .iter()
+                .map(|name| AnkiField {
+                    name: (*name).to_owned(),
+                })
+                .collect(),
+            id: 1,
+            name: "Spanish".to_owned(),
+            sortf: 0,
+            tmpls: vec![],
+        }
+    }
+
+    fn get_pundit_note() -> Note {
+        Note {
+            filename: PathBuf::from("anki_note.org"),
+            title: "anki note".to_owned(),
+            links: vec![],
+            backlinks: vec![],
+            tags: vec![],
+        }
+    }
+
+    /// Run the function on a conflict in the English field of the note.
+    fn with_conflict<T>(f: impl FnOnce(&NoteConflict) -> T) -> T {
+        let anki = get_anki_note(&["palabra", "word"]);
+        let pundit = get_anki_note(&["palabra", "word CHANGE"]);
+        let pundit_note = get_pundit_note();
+        f(&NoteConflict {
+            anki: &anki,
+            pundit: &pundit,
+            pundit_note: &pundit_note,
+        })
+    }
+
+    fn read(text: &str) -> Result<Vec<String>> {
+        with_conflict(|conflict| conflict.read_merged_text(&get_model(), text))
+    }
+
+    fn resolve(values: &[&str]) -> Resolution {
+        let values = values.iter().map(|value| (*value).to_owned()).collect();
+        with_conflict(|conflict| conflict.get_resolution_for_values(values))
+    }
+
+    #[test]
+    fn read_merged_text_fields() {
+        let values = read("@@@ Spanish\npalabra\n@@@ English\nword\nsecond line\n").unwrap();
+        assert_eq!(values, vec!["palabra", "word\nsecond line"]);
+    }
+
+    #[test]
+    fn read_merged_text_conflict_markers_left() {
+        let text = "@@@ Spanish\npalabra\n@@@ English\n<<<<<<< pundit\nword CHANGE\n=======\nword\n>>>>>>> anki\n";
+        assert!(read(text).is_err());
+    }
+
+    #[test]
+    fn read_merged_text_conflict_separator_left() {
+        assert!(read("@@@ Spanish\npalabra\n@@@ English\nword\n=======\nword CHANGE\n").is_err());
+    }
+
+    #[test]
+    fn read_merged_text_missing_field() {
+        assert!(read("@@@ Spanish\npalabra\n").is_err());
+    }
+
+    #[test]
+    fn read_merged_text_text_before_first_header() {
+        assert!(read("palabra\n@@@ Spanish\npalabra\n@@@ English\nword\n").is_err());
+    }
+
+    #[test]
+    fn resolution_for_values_equal_to_pundit() {
+        assert!(matches!(
+            resolve(&["palabra", "word CHANGE"]),
+            Resolution::Pundit
+        ));
+    }
+
+    #[test]
+    fn resolution_for_values_equal_to_anki() {
+        assert!(matches!(resolve(&["palabra", "word"]), Resolution::Anki));
+    }
+
+    #[test]
+    fn resolution_for_merged_values() {
+        match resolve(&["palabra", "the word"]) {
+            Resolution::Merged(values) => assert_eq!(values, vec!["palabra", "the word"]),
+            _ => panic!("Expected a merged resolution"),
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use anyhow::Result;
+use log::info;
+use serde::Serialize;
+
+use super::get_managed_anki_tags;
+use super::get_merged_tags;
+use super::get_previous_tags;
+use super::get_synced_csum;
+use super::get_tag_set;
+use super::join_tags;
+use super::pankit_note_info::PankitDatabase;
+use super::pankit_note_info::PankitId;
+use super::Action;
+use super::SyncPolicy;
+use crate::anki::anki_card::AnkiCard;
+use crate::anki::anki_collection::AnkiCollection;
+use crate::anki::anki_note::AnkiNote;
+use crate::anki::is_note_id_field;
+use crate::anki::ANKI_FIELD_SEPARATOR;
+use crate::args::AnkiDeletionHandling;
+use crate::args::OutputFormat;
+use crate::args::PunditRemovalHandling;
+use crate::note::Note;
+
+#[derive(Serialize)]
+pub struct AddedNote {
+    pub id: PankitId,
+    pub file: String,
+    pub num_cards: usize,
+}
+
+#[derive(Serialize)]
+pub struct FieldChange {
+    pub name: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize)]
+pub struct ChangedNote {
+    pub id: PankitId,
+    pub file: String,
+    /// Only the fields whose values change, including the tags managed by pankit
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Serialize)]
+pub struct FieldConflict {
+    pub name: String,
+    pub pundit: String,
+    pub anki: String,
+}
+
+#[derive(Serialize)]
+pub struct ConflictingNote {
+    pub id: PankitId,
+    pub file: String,
+    /// Only the fields whose values differ between pundit and anki, including the tags managed by pankit
+    pub fields: Vec<FieldConflict>,
+}
+
+#[derive(Serialize)]
+pub struct RemovedNote {
+    pub id: PankitId,
+    /// The pundit file containing the note, if it is still in pundit
+    pub file: Option<String>,
+    /// What the synchronization will do with the note
+    pub handling: String,
+}
+
+/// Everything a synchronization would change, without changing anything.
+#[derive(Serialize, Default)]
+pub struct SyncSummary {
+    /// Notes which will be added to anki
+    pub added: Vec<AddedNote>,
+    /// Notes whose pundit contents will be written to anki
+    pub changed_in_anki: Vec<ChangedNote>,
+    /// Notes whose anki contents will be written to pundit
+    pub changed_in_pundit: Vec<ChangedNote>,
+    /// Notes which are up to date in anki and pundit but whose pankit database entry changes
+    pub database_updates: Vec<PankitId>,
+    /// Notes whose contents conflict and which the synchronization cannot resolve automatically
+    pub conflicts: Vec<ConflictingNote>,
+    /// Notes which are still in anki but were removed from pundit
+    pub removed_in_pundit: Vec<RemovedNote>,
+    /// Notes which are still in pundit but were deleted in anki
+    pub deleted_in_anki: Vec<RemovedNote>,
+}
+
+impl SyncSummary {
+    pub(super) fn from_actions(
+        collection: &AnkiCollection,
+        pankit_db: &PankitDatabase,
+        anki_notes: &[AnkiNote],
+        anki_notes_and_cards: &[(&Note, AnkiNote, Vec<AnkiCard>)],
+        actions: &[Action],
+        policy: SyncPolicy,
+    ) -> SyncSummary {
+        let find_pundit_version = |id: PankitId| {
+            anki_notes_and_cards
+                .iter()
+                .find(|(_, anki_note, _)| anki_note.id == id)
+        };
+        let get_file = |id: PankitId| {
+            find_pundit_version(id)
+                .map(|(pundit_note, _, _)| get_filename(pundit_note))
+                .unwrap_or_default()
+        };
+        let mut summary = SyncSummary::default();
+        for action in actions.iter() {
+            match action {
+                Action::AddNoteAndCards(anki_note, anki_cards) => summary.added.push(AddedNote {
+                    id: anki_note.id,
+                    file: get_file(anki_note.id),
+                    num_cards: anki_cards.len(),
+                }),
+                Action::ChangeInDatabase(anki_note, pundit_version) => {
+                    if database_entry_changes(pankit_db, anki_note, pundit_version) {
+                        summary.database_updates.push(anki_note.id);
+                    }
+                }
+                Action::ChangeInDatabaseAndAnki(pundit_version) => {
+                    if let Some(anki_version) =
+                        anki_notes.iter().find(|note| note.id == pundit_version.id)
+                    {
+                        let mut fields =
+                            get_field_changes(collection, anki_version, pundit_version);
+                        fields.extend(get_tag_change(pankit_db, anki_version, pundit_version));
+                        summary.changed_in_anki.push(ChangedNote {
+                            id: pundit_version.id,
+                            file: get_file(pundit_version.id),
+                            fields,
+                        });
+                    }
+                }
+                Action::ChangeInDatabaseAndPundit(anki_version, pundit_version, pundit_note) => {
+                    summary.changed_in_pundit.push(ChangedNote {
+                        id: anki_version.id,
+                        file: get_filename(pundit_note),
+                        fields: get_field_changes(collection, pundit_version, anki_version),
+                    });
+                    if let Some(tag_change) =
+                        get_tag_change(pankit_db, anki_version, pundit_version)
+                    {
+                        summary.changed_in_anki.push(ChangedNote {
+                            id: anki_version.id,
+                            file: get_filename(pundit_note),
+                            fields: vec![tag_change],
+                        });
+                    }
+                }
+                Action::AskUserConflict(conflict) => {
+                    let mut fields: Vec<FieldConflict> =
+                        get_field_changes(collection, conflict.pundit, conflict.anki)
+                            .into_iter()
+                            .map(|change| FieldConflict {
+                                name: change.name,
+                                pundit: change.old,
+                                anki: change.new,
+                            })
+                            .collect();
+                    let pundit_tags = get_tag_set(&conflict.pundit.tags);
+                    let anki_tags = get_managed_anki_tags(
+                        conflict.anki,
+                        conflict.pundit,
+                        get_previous_tags(pankit_db, conflict.anki.id),
+                    );
+                    if pundit_tags != anki_tags {
+                        fields.push(FieldConflict {
+                            name: "tags".to_owned(),
+                            pundit: join_tags(pundit_tags),
+                            anki: join_tags(anki_tags),
+                        });
+                    }
+                    summary.conflicts.push(ConflictingNote {
+                        id: conflict.anki.id,
+                        file: get_filename(conflict.pundit_note),
+                        fields,
+                    })
+                }
+                Action::RemoveFromDatabase(id) => summary.database_updates.push(*id),
+                Action::RemovedInPundit(anki_note) => summary.removed_in_pundit.push(RemovedNote {
+                    id: anki_note.id,
+                    file: None,
+                    handling: get_pundit_removal_name(policy.removed_in_pundit).to_owned(),
+                }),
+                Action::DeletedInAnki(anki_note, _, pundit_note) => {
+                    summary.deleted_in_anki.push(RemovedNote {
+                        id: anki_note.id,
+                        file: Some(get_filename(pundit_note)),
+                        handling: get_anki_deletion_name(policy.deleted_in_anki).to_owned(),
+                    })
+                }
+                Action::ChangeInDatabaseAnkiAndPundit(merged_version, pundit_note) => {
+                    if let Some((_, pundit_version, _)) = find_pundit_version(merged_version.id) {
+                        summary.changed_in_pundit.push(ChangedNote {
+                            id: merged_version.id,
+                            file: get_filename(pundit_note),
+                            fields: get_field_changes(collection, pundit_version, merged_version),
+                        });
+                    }
+                    if let Some(anki_version) =
+                        anki_notes.iter().find(|note| note.id == merged_version.id)
+                    {
+                        let mut fields =
+                            get_field_changes(collection, anki_version, merged_version);
+                        fields.extend(get_tag_change(pankit_db, anki_version, merged_version));
+                        summary.changed_in_anki.push(ChangedNote {
+                            id: merged_version.id,
+                            file: get_filename(pundit_note),
+                            fields,
+                        });
+                    }
+                }
+                Action::RemoveFromPundit(..)
+                | Action::DeleteInAnki(_)
+                | Action::SuspendInAnki(_)
+                | Action::MoveToOrphanedDeck(_) => {
+                    unreachable!("Only created when resolving removals")
+                }
+            }
+        }
+        summary
+    }
+
+    pub fn show(&self, output: &OutputFormat) -> Result<()> {
+        match output {
+            OutputFormat::Text => self.show_text(),
+            OutputFormat::Json => info!("{}", serde_json::to_string_pretty(self)?),
+        }
+        Ok(())
+    }
+
+    fn show_text(&self) {
+        info!("Notes to add ({}):", self.added.len());
+        for note in self.added.iter() {
+            info!("\t{}\t{}\t{} cards", note.id, note.file, note.num_cards);
+        }
+        let changes = [
+            ("Notes to change in anki", &self.changed_in_anki),
+            ("Notes to change in pundit", &self.changed_in_pundit),
+        ];
+        for (name, list) in changes.iter() {
+            info!("{} ({}):", name, list.len());
+            for note in list.iter() {
+                info!("\t{}\t{}", note.id, note.file);
+                for field in note.fields.iter() {
+                    info!("\t\t{}:", field.name);
+                    for line in field.old.lines() {
+                        info!("\t\t- {}", line);
+                    }
+                    for line in field.new.lines() {
+                        info!("\t\t+ {}", line);
+                    }
+                }
+            }
+        }
+        info!("Database updates ({}):", self.database_updates.len());
+        for id in self.database_updates.iter() {
+            info!("\t{}", id);
+        }
+        info!("Conflicts ({}):", self.conflicts.len());
+        for note in self.conflicts.iter() {
+            info!("\t{}\t{}", note.id, note.file);
+            for field in note.fields.iter() {
+                info!("\t\t{}:", field.name);
+                for line in field.pundit.lines() {
+                    info!("\t\tpundit | {}", line);
+                }
+                for line in field.anki.lines() {
+                    info!("\t\tanki   | {}", line);
+                }
+            }
+        }
+        let removals = [
+            ("Notes removed in pundit", &self.removed_in_pundit),
+            ("Notes deleted in anki", &self.deleted_in_anki),
+        ];
+        for (name, list) in removals.iter() {
+            info!("{} ({}):", name, list.len());
+            for note in list.iter() {
+                info!(
+                    "\t{}\t{}\t{}",
+                    note.id,
+                    note.file.as_deref().unwrap_or("-"),
+                    note.handling
+                );
+            }
+        }
+    }
+}
+
+fn database_entry_changes(
+    pankit_db: &PankitDatabase,
+    anki_note: &AnkiNote,
+    anki_note_pundit: &AnkiNote,
+) -> bool {
+    let tags = get_tag_set(&anki_note_pundit.tags);
+    match pankit_db.get(&anki_note.id) {
+        None => true,
+        Some(entry) => {
+            entry.csum != get_synced_csum(&anki_note.flds, &tags)
+                || entry.mod_ != anki_note.mod_
+                || !entry.tags.iter().eq(tags.iter())
+        }
+    }
+}
+
+/// The change of the tags of the anki note when the managed tags are replaced by the tags from pundit.
+fn get_tag_change(
+    pankit_db: &PankitDatabase,
+    anki_note_anki: &AnkiNote,
+    anki_note_pundit: &AnkiNote,
+) -> Option<FieldChange> {
+    let old_tags = get_tag_set(&anki_note_anki.tags);
+    let new_tags = get_merged_tags(
+        &anki_note_anki.tags,
+        get_previous_tags(pankit_db, anki_note_anki.id),
+        &anki_note_pundit.tags,
+    );
+    match old_tags == new_tags {
+        true => None,
+        false => Some(FieldChange {
+            name: "tags".to_owned(),
+            old: join_tags(old_tags),
+            new: join_tags(new_tags),
+        }),
+    }
+}
+
+/// The fields whose values differ between the two versions of the note.
+fn get_field_changes(
+    collection: &AnkiCollection,
+    old: &AnkiNote,
+    new: &AnkiNote,
+) -> Vec<FieldChange> {
+    let model = collection.models.iter().find(|model| model.id == new.mid);
+    old.flds
+        .split(ANKI_FIELD_SEPARATOR)
+        .zip(new.flds.split(ANKI_FIELD_SEPARATOR))
+        .enumerate()
+        .map(|(i, (old, new))| {
+            let name = model
+                .and_then(|model| model.flds.get(i))
+                .map(|field| field.name.clone())
+                .unwrap_or_else(|| format!("Field {}", i + 1));
+            (name, old, new)
+        })
+        .filter(|(name, old, new)| old != new && !is_note_id_field(name))
+        .map(|(name, old, new)| FieldChange {
+            name,
+            old: old.to_owned(),
+            new: new.to_owned(),
+        })
+        .collect()
+}
+
+fn get_filename(note: &Note) -> String {
+    note.filename.to_str().unwrap().to_owned()
+}
+
+fn get_pundit_removal_name(handling: PunditRemovalHandling) -> &'static str {
+    match handling {
+        PunditRemovalHandling::Keep => "keep",
+        PunditRemovalHandling::Delete => "delete",
+        PunditRemovalHandling::Suspend => "suspend",
+        PunditRemovalHandling::Orphan => "orphan",
+    }
+}
+
+fn get_anki_deletion_name(handling: AnkiDeletionHandling) -> &'static str {
+    match handling {
+        AnkiDeletionHandling::Keep => "keep",
+        AnkiDeletionHandling::ReAdd => "re-add",
+        AnkiDeletionHandling::Remove => "remove",
+    }
+}
@@ -1,10 +1,16 @@
+pub mod conflict_resolution;
+pub mod dry_run;
 pub mod pankit_note_info;
 pub mod pankit_yaml_block;
+pub mod pankit_yaml_edit;
 pub mod pankit_yaml_note;
 
 use std::cmp::Ordering::Equal;
 use std::cmp::Ordering::Greater;
 use std::cmp::Ordering::Less;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -12,15 +18,22 @@ use std::path::PathBuf;
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
+use chrono::Local;
 use log::error;
 use log::info;
 use rand::Rng;
 use regex::Regex;
 use rusqlite::Connection;
 
+use self::conflict_resolution::NoteConflict;
+use self::conflict_resolution::Resolution;
+use self::dry_run::SyncSummary;
 use self::pankit_note_info::PankitDatabase;
+use self::pankit_note_info::PankitId;
 use self::pankit_note_info::PankitNoteInfo;
 use self::pankit_yaml_block::PankitYamlBlock;
+use self::pankit_yaml_edit::remove_note;
+use self::pankit_yaml_edit::replace_field_values;
 use crate::anki::add_anki_card;
 use crate::anki::add_anki_note;
 use crate::anki::anki_card::AnkiCard;
@@ -28,39 +41,74 @@ use crate::anki::anki_collection::AnkiCollection;
 use crate::anki::anki_deck::AnkiDeck;
 use crate::anki::anki_model::AnkiModel;
 use crate::anki::anki_note::AnkiNote;
+use crate::anki::backup_collection;
+use crate::anki::begin_exclusive_transaction;
 use crate::anki::close_connection;
+use crate::anki::delete_anki_note;
 use crate::anki::find_anki_note_in_collection;
+use crate::anki::format_anki_tags;
+use crate::anki::get_anki_note_with_field_values;
 use crate::anki::get_csum;
+use crate::anki::get_deck_by_name;
 use crate::anki::get_new_anki_note_and_cards;
 use crate::anki::get_unix_time;
 use crate::anki::is_note_id_field;
+use crate::anki::move_anki_cards_to_deck;
+use crate::anki::read_anki_note_tags;
 use crate::anki::read_collection;
 use crate::anki::read_notes;
+use crate::anki::suspend_anki_cards;
 use crate::anki::update_anki_note_contents;
+use crate::anki::update_anki_note_tags;
 use crate::anki::AnkiNoteInfo;
+use crate::anki::ANKI_FIELD_SEPARATOR;
+use crate::args::AnkiDeletionHandling;
 use crate::args::ConflictHandling;
+use crate::args::OutputFormat;
+use crate::args::PunditRemovalHandling;
+use crate::args::TagSource;
+use crate::config::ANKI_BACKUP_DATE_FORMAT_STR;
 use crate::config::ANKI_BLOCK_NOTE_TEMPLATE;
 use crate::config::ANKI_FULL_NOTE_TEMPLATE;
 use crate::config::ANKI_NOTE_FIELD_TEMPLATE;
 use crate::config::ID_MULTIPLIER;
+use crate::config::ORPHANED_DECK_NAME;
+use crate::dir_utils::get_relative_path;
+use crate::file_utils::write_file_atomically;
 use crate::fzf::select_interactively;
 use crate::named::get_by_name;
 use crate::note::Note;
 use crate::notes::Notes;
 
+/// The tags of the anki notes made from the pundit notes are the tags managed by pankit.
 #[derive(Debug)]
 enum Action<'a> {
-    ChangeInDatabase(&'a AnkiNote),
+    /// The anki note and the anki note made from the pundit note
+    ChangeInDatabase(&'a AnkiNote, &'a AnkiNote),
     ChangeInDatabaseAndAnki(&'a AnkiNote),
-    ChangeInDatabaseAndPundit(&'a AnkiNote),
+    /// The anki note, the anki note made from the pundit note and the pundit note
+    ChangeInDatabaseAndPundit(&'a AnkiNote, &'a AnkiNote, &'a Note),
+    /// A merged version of a conflicting note which is written to anki and pundit
+    ChangeInDatabaseAnkiAndPundit(AnkiNote, &'a Note),
     AddNoteAndCards(&'a AnkiNote, &'a [AnkiCard]),
     AskUserConflict(NoteConflict<'a>),
+    /// The note is still in pundit but was deleted in anki after it was synchronized
+    DeletedInAnki(&'a AnkiNote, &'a [AnkiCard], &'a Note),
+    /// The note is still in anki but was removed from pundit after it was synchronized
+    RemovedInPundit(&'a AnkiNote),
+    RemoveFromDatabase(PankitId),
+    RemoveFromPundit(&'a AnkiNote, &'a Note),
+    DeleteInAnki(&'a AnkiNote),
+    SuspendInAnki(&'a AnkiNote),
+    MoveToOrphanedDeck(&'a AnkiNote),
 }
 
-#[derive(Debug)]
-struct NoteConflict<'a> {
-    anki: &'a AnkiNote,
-    pundit: &'a AnkiNote,
+/// How to deal with changes which cannot be synchronized automatically.
+#[derive(Debug, Clone, Copy)]
+pub struct SyncPolicy {
+    pub conflict_handling: ConflictHandling,
+    pub removed_in_pundit: PunditRemovalHandling,
+    pub deleted_in_anki: AnkiDeletionHandling,
 }
 
 impl<'a> Action<'a> {
@@ -69,72 +117,264 @@ impl<'a> Action<'a> {
     }
 }
 
+/// The new contents of the pundit files changed during a synchronization. They are only written
+/// once the changes to the anki collection are committed.
+#[derive(Default)]
+struct PunditWrites {
+    contents: HashMap<PathBuf, String>,
+}
+
+impl PunditWrites {
+    /// The contents of the pundit note, including the changes made so far.
+    fn get_contents(&self, pundit_note: &Note) -> Result<String> {
+        match self.contents.get(&pundit_note.filename) {
+            Some(contents) => Ok(contents.clone()),
+            None => pundit_note
+                .get_contents()
+                .context("While reading file contents"),
+        }
+    }
+
+    fn set_contents(&mut self, pundit_note: &Note, contents: String) {
+        self.contents.insert(pundit_note.filename.clone(), contents);
+    }
+
+    fn write(self) -> Result<()> {
+        for (filename, contents) in self.contents {
+            write_file_atomically(&filename, &contents)?;
+        }
+        Ok(())
+    }
+}
+
+/// Synchronize the anki collection with the pundit notes. All changes to the collection are made in a
+/// single transaction which is only committed once everything else succeeded. The pundit notes and
+/// the pankit database are written afterwards, so that an interrupted run leaves the pankit database
+/// behind the collection, which the next run recovers from by comparing checksums.
 pub fn update_anki(
     path: &Path,
     pankit_db_path: &Path,
     notes: &Notes,
-    conflict_handling: ConflictHandling,
+    policy: SyncPolicy,
+    tag_sources: &[TagSource],
+    backup: bool,
 ) -> Result<()> {
     let mut pankit_db = read_pankit_database(pankit_db_path)?;
-    let connection = Connection::open(path).unwrap();
-    let anki_notes = read_notes(&connection)?;
-    let collection = read_collection(&connection)?;
-    update_from_pundit_contents(
-        &connection,
+    let mut connection =
+        Connection::open(path).context(format!("While opening anki collection {:?}", path))?;
+    if backup {
+        backup_collection(&connection, &get_backup_path(path))?;
+    }
+    let transaction = begin_exclusive_transaction(&mut connection)?;
+    let anki_notes = read_notes(&transaction)?;
+    let collection = read_collection(&transaction)?;
+    let pundit_writes = update_from_pundit_contents(
+        &transaction,
         notes,
         &collection,
         &anki_notes,
         &mut pankit_db,
-        conflict_handling,
+        policy,
+        tag_sources,
     )?;
+    let data = serde_yaml::to_string(&pankit_db).context("While converting pankit db to yaml")?;
+    transaction
+        .commit()
+        .context("While committing changes to anki collection")?;
     close_connection(connection)?;
-    write_pankit_database(pankit_db_path, &pankit_db)?;
+    pundit_writes.write()?;
+    write_file_atomically(pankit_db_path, &data).context("Unable to write pankit file")?;
     Ok(())
 }
 
-fn write_pankit_database(pankit_db_path: &Path, pankit_db: &PankitDatabase) -> Result<()> {
-    let data = serde_yaml::to_string(pankit_db).context("While converting pankit db to yaml")?;
-    fs::write(pankit_db_path, data).context("Unable to write pankit file")?;
-    Ok(())
+/// The path of a timestamped backup next to the collection.
+fn get_backup_path(path: &Path) -> PathBuf {
+    let mut backup_name = path.file_name().unwrap().to_owned();
+    backup_name.push(format!(
+        ".{}.backup",
+        Local::now().format(ANKI_BACKUP_DATE_FORMAT_STR)
+    ));
+    path.with_file_name(backup_name)
+}
+
+/// Show what update_anki would change without touching the anki or pankit database or the pundit notes.
+/// Conflicts are only resolved if the conflict handling does so without asking the user.
+pub fn show_anki_update(
+    path: &Path,
+    pankit_db_path: &Path,
+    notes: &Notes,
+    policy: SyncPolicy,
+    tag_sources: &[TagSource],
+    output: &OutputFormat,
+) -> Result<()> {
+    let pankit_db = read_pankit_database_if_exists(pankit_db_path)?.unwrap_or_default();
+    let connection =
+        Connection::open(path).context(format!("While opening anki collection {:?}", path))?;
+    let anki_notes = read_notes(&connection)?;
+    let collection = read_collection(&connection)?;
+    close_connection(connection)?;
+    let anki_notes_and_cards =
+        get_anki_notes_and_cards_for_pundit_notes(&collection, notes, tag_sources)?;
+    let actions = get_actions(&pankit_db, &anki_notes, &anki_notes_and_cards);
+    let actions = match policy.conflict_handling {
+        ConflictHandling::Anki | ConflictHandling::Pundit => {
+            filter_actions_for_conflicts(&collection, actions, policy.conflict_handling)?
+        }
+        _ => actions,
+    };
+    SyncSummary::from_actions(
+        &collection,
+        &pankit_db,
+        &anki_notes,
+        &anki_notes_and_cards,
+        &actions,
+        policy,
+    )
+    .show(output)
 }
 
 fn read_pankit_database(pankit_db_path: &Path) -> Result<PankitDatabase> {
-    let mb_data = fs::read_to_string(pankit_db_path);
-    match mb_data {
-        Ok(data) => Ok(serde_yaml::from_str(&data).context("Reading pankit database contents")?),
-        Err(_err) => {
+    match read_pankit_database_if_exists(pankit_db_path)? {
+        Some(pankit_db) => Ok(pankit_db),
+        None => {
             error!("Pankit database file does not exist: Assuming empty database.");
             Ok(PankitDatabase::new())
         }
     }
 }
 
-pub fn update_from_pundit_contents(
+fn read_pankit_database_if_exists(pankit_db_path: &Path) -> Result<Option<PankitDatabase>> {
+    match fs::read_to_string(pankit_db_path) {
+        Ok(data) => Ok(Some(
+            serde_yaml::from_str(&data).context("Reading pankit database contents")?,
+        )),
+        Err(_err) => Ok(None),
+    }
+}
+
+fn update_from_pundit_contents(
     connection: &Connection,
     notes: &Notes,
     collection: &AnkiCollection,
     anki_notes: &[AnkiNote],
     mut pankit_db: &mut PankitDatabase,
-    conflict_handling: ConflictHandling,
-) -> Result<()> {
-    let anki_notes_and_cards = get_anki_notes_and_cards_for_pundit_notes(collection, notes)?;
-    let actions: Vec<Action> = anki_notes_and_cards
-        .iter()
-        .map(|(anki_note, anki_cards)| get_action(pankit_db, anki_notes, anki_note, anki_cards))
-        .collect();
-    let filtered_actions = filter_actions_for_conflicts(actions, conflict_handling)?;
+    policy: SyncPolicy,
+    tag_sources: &[TagSource],
+) -> Result<PunditWrites> {
+    let anki_notes_and_cards =
+        get_anki_notes_and_cards_for_pundit_notes(collection, notes, tag_sources)?;
+    let actions = get_actions(pankit_db, anki_notes, &anki_notes_and_cards);
+    let actions = apply_removal_handling(actions, policy);
+    let filtered_actions =
+        filter_actions_for_conflicts(collection, actions, policy.conflict_handling)?;
+    let mut pundit_writes = PunditWrites::default();
     for action in filtered_actions {
-        execute_action(connection, &mut pankit_db, action)?;
+        execute_action(
+            connection,
+            collection,
+            &mut pankit_db,
+            &mut pundit_writes,
+            action,
+        )?;
     }
-    Ok(())
+    Ok(pundit_writes)
+}
+
+/// The actions for all notes in pundit and all notes which were removed from pundit, before
+/// applying the removal and conflict handling.
+fn get_actions<'a>(
+    pankit_db: &PankitDatabase,
+    anki_notes: &'a [AnkiNote],
+    anki_notes_and_cards: &'a [(&'a Note, AnkiNote, Vec<AnkiCard>)],
+) -> Vec<Action<'a>> {
+    let mut actions: Vec<Action> = anki_notes_and_cards
+        .iter()
+        .map(|(pundit_note, anki_note, anki_cards)| {
+            get_action(pankit_db, anki_notes, pundit_note, anki_note, anki_cards)
+        })
+        .collect();
+    actions.extend(get_removal_actions(
+        pankit_db,
+        anki_notes,
+        anki_notes_and_cards,
+    ));
+    actions
+}
+
+/// The actions for all notes in the pankit database which are no longer in the pundit notes.
+fn get_removal_actions<'a>(
+    pankit_db: &PankitDatabase,
+    anki_notes: &'a [AnkiNote],
+    anki_notes_and_cards: &[(&Note, AnkiNote, Vec<AnkiCard>)],
+) -> Vec<Action<'a>> {
+    let pundit_ids: HashSet<PankitId> = anki_notes_and_cards
+        .iter()
+        .map(|(_, anki_note, _)| anki_note.id)
+        .collect();
+    let mut removed_ids: Vec<PankitId> = pankit_db
+        .keys()
+        .filter(|id| !pundit_ids.contains(id))
+        .copied()
+        .collect();
+    removed_ids.sort_unstable();
+    removed_ids
+        .into_iter()
+        .map(
+            |id| match anki_notes.iter().find(|anki_note| anki_note.id == id) {
+                Some(anki_note) => Action::RemovedInPundit(anki_note),
+                None => Action::RemoveFromDatabase(id),
+            },
+        )
+        .collect()
+}
+
+fn apply_removal_handling(actions: Vec<Action>, policy: SyncPolicy) -> Vec<Action> {
+    actions
+        .into_iter()
+        .filter_map(|action| match action {
+            Action::DeletedInAnki(anki_note, anki_cards, pundit_note) => {
+                match policy.deleted_in_anki {
+                    AnkiDeletionHandling::Keep => {
+                        info!(
+                            "Note {} in {} was deleted in anki. Not adding it again.",
+                            anki_note.id,
+                            pundit_note.filename.to_str().unwrap()
+                        );
+                        None
+                    }
+                    AnkiDeletionHandling::ReAdd => {
+                        Some(Action::AddNoteAndCards(anki_note, anki_cards))
+                    }
+                    AnkiDeletionHandling::Remove => {
+                        Some(Action::RemoveFromPundit(anki_note, pundit_note))
+                    }
+                }
+            }
+            Action::RemovedInPundit(anki_note) => match policy.removed_in_pundit {
+                PunditRemovalHandling::Keep => {
+                    info!(
+                        "Note {} was removed from the pundit notes. Keeping it in anki.",
+                        anki_note.id
+                    );
+                    None
+                }
+                PunditRemovalHandling::Delete => Some(Action::DeleteInAnki(anki_note)),
+                PunditRemovalHandling::Suspend => Some(Action::SuspendInAnki(anki_note)),
+                PunditRemovalHandling::Orphan => Some(Action::MoveToOrphanedDeck(anki_note)),
+            },
+            _ => Some(action),
+        })
+        .collect()
 }
 
-fn filter_actions_for_conflicts(
-    actions: Vec<Action>,
+fn filter_actions_for_conflicts<'a>(
+    collection: &AnkiCollection,
+    actions: Vec<Action<'a>>,
     conflict_handling: ConflictHandling,
-) -> Result<Vec<Action>> {
+) -> Result<Vec<Action<'a>>> {
     match conflict_handling {
-        ConflictHandling::GiveError => get_actions_if_no_conflict(actions),
+        ConflictHandling::GiveError => get_actions_if_no_conflict(collection, actions),
+        ConflictHandling::Interactive => resolve_conflicts_interactively(collection, actions),
         ConflictHandling::Ignore => Ok(filter_conflict_actions(actions)),
         ConflictHandling::Anki => Ok(use_anki_in_case_of_conflicts(actions)),
         ConflictHandling::Pundit => Ok(use_pundit_in_case_of_conflicts(actions)),
@@ -155,7 +395,11 @@ fn use_anki_in_case_of_conflicts(actions: Vec<Action>) -> Vec<Action> {
     actions
         .into_iter()
         .map(|action| match action {
-            Action::AskUserConflict(conflict) => Action::ChangeInDatabaseAndPundit(conflict.anki),
+            Action::AskUserConflict(conflict) => Action::ChangeInDatabaseAndPundit(
+                conflict.anki,
+                conflict.pundit,
+                conflict.pundit_note,
+            ),
             _ => action,
         })
         .collect()
@@ -168,10 +412,47 @@ fn filter_conflict_actions(actions: Vec<Action>) -> Vec<Action> {
         .collect()
 }
 
-fn get_actions_if_no_conflict(actions: Vec<Action>) -> Result<Vec<Action>> {
+fn resolve_conflicts_interactively<'a>(
+    collection: &AnkiCollection,
+    actions: Vec<Action<'a>>,
+) -> Result<Vec<Action<'a>>> {
+    let mut result = vec![];
+    for action in actions {
+        match action {
+            Action::AskUserConflict(conflict) => {
+                match conflict.resolve_interactively(collection)? {
+                    Resolution::Pundit => {
+                        result.push(Action::ChangeInDatabaseAndAnki(conflict.pundit))
+                    }
+                    Resolution::Anki => result.push(Action::ChangeInDatabaseAndPundit(
+                        conflict.anki,
+                        conflict.pundit,
+                        conflict.pundit_note,
+                    )),
+                    Resolution::Merged(values) => {
+                        result.push(Action::ChangeInDatabaseAnkiAndPundit(
+                            get_anki_note_with_field_values(collection, conflict.pundit, &values)?,
+                            conflict.pundit_note,
+                        ))
+                    }
+                    Resolution::Skip => {}
+                }
+            }
+            _ => result.push(action),
+        }
+    }
+    Ok(result)
+}
+
+fn get_actions_if_no_conflict<'a>(
+    collection: &AnkiCollection,
+    actions: Vec<Action<'a>>,
+) -> Result<Vec<Action<'a>>> {
     if actions.iter().any(|action| action.is_conflict()) {
-        for conflict in actions.iter().filter(|action| action.is_conflict()) {
-            error!("{:?}", conflict);
+        for action in actions.iter() {
+            if let Action::AskUserConflict(conflict) = action {
+                conflict.show(collection);
+            }
         }
         Err(anyhow!("There are conflicting notes!"))
     } else {
@@ -181,7 +462,9 @@ fn get_actions_if_no_conflict(actions: Vec<Action>) -> Result<Vec<Action>> {
 
 fn execute_action(
     connection: &Connection,
+    collection: &AnkiCollection,
     pankit_db: &mut PankitDatabase,
+    pundit_writes: &mut PunditWrites,
     action: Action,
 ) -> Result<()> {
     match action {
@@ -192,21 +475,60 @@ fn execute_action(
                 add_anki_card(&connection, anki_card).context("While adding anki card")?;
             }
         }
-        Action::ChangeInDatabase(note) => {
-            update_database_entry(pankit_db, note);
+        Action::ChangeInDatabase(note, pundit_version) => {
+            update_database_entry(pankit_db, note, &pundit_version.tags);
         }
         Action::ChangeInDatabaseAndAnki(note) => {
             update_anki_note_contents(connection, note)?;
-            update_database_entry(pankit_db, note);
+            update_managed_tags(connection, pankit_db, note, note.mod_)?;
+            update_database_entry(pankit_db, note, &note.tags);
         }
         Action::AskUserConflict(conflict) => {
-            error!(
-                "There is a conflict between note contents for id {}:Pundit note contents:\n{}\nAnki note contents:\n{}",
-                conflict.anki.id, conflict.anki.flds, conflict.pundit.flds
+            conflict.show(collection);
+        }
+        Action::DeletedInAnki(..) | Action::RemovedInPundit(_) => {
+            unreachable!("Replaced according to the removal handling")
+        }
+        Action::RemoveFromDatabase(id) => {
+            pankit_db.remove(&id);
+        }
+        Action::RemoveFromPundit(note, pundit_note) => {
+            info!(
+                "Removing note {} from {}",
+                note.id,
+                pundit_note.filename.to_str().unwrap()
             );
+            let contents = pundit_writes.get_contents(pundit_note)?;
+            pundit_writes.set_contents(pundit_note, remove_note(&contents, note.id)?);
+            pankit_db.remove(&note.id);
+        }
+        Action::DeleteInAnki(note) => {
+            delete_anki_note(connection, note.id)?;
+            pankit_db.remove(&note.id);
+        }
+        Action::SuspendInAnki(note) => {
+            suspend_anki_cards(connection, note.id)?;
+            // The note is no longer synchronized, so that the cards are only suspended once.
+            pankit_db.remove(&note.id);
+        }
+        Action::MoveToOrphanedDeck(note) => {
+            let deck = get_deck_by_name(collection, ORPHANED_DECK_NAME).context(format!(
+                "Add a deck named {} to move removed notes there",
+                ORPHANED_DECK_NAME
+            ))?;
+            move_anki_cards_to_deck(connection, note.id, deck)?;
+            pankit_db.remove(&note.id);
+        }
+        Action::ChangeInDatabaseAndPundit(note, pundit_version, pundit_note) => {
+            update_pundit_note_contents(collection, pundit_writes, note, pundit_note)?;
+            update_managed_tags(connection, pankit_db, pundit_version, get_unix_time())?;
+            update_database_entry(pankit_db, note, &pundit_version.tags);
         }
-        Action::ChangeInDatabaseAndPundit(_note) => {
-            todo!();
+        Action::ChangeInDatabaseAnkiAndPundit(note, pundit_note) => {
+            update_anki_note_contents(connection, &note)?;
+            update_pundit_note_contents(collection, pundit_writes, &note, pundit_note)?;
+            update_managed_tags(connection, pankit_db, &note, note.mod_)?;
+            update_database_entry(pankit_db, &note, &note.tags);
         }
     };
     Ok(())
@@ -215,27 +537,36 @@ fn execute_action(
 fn get_action<'a>(
     pankit_db: &PankitDatabase,
     anki_notes: &'a [AnkiNote],
+    pundit_note: &'a Note,
     anki_note: &'a AnkiNote,
     anki_cards: &'a [AnkiCard],
 ) -> Action<'a> {
     match find_anki_note_in_collection(anki_notes, anki_note) {
+        None if pankit_db.contains_key(&anki_note.id) => {
+            Action::DeletedInAnki(anki_note, anki_cards, pundit_note)
+        }
         None => Action::AddNoteAndCards(anki_note, anki_cards),
         Some(anki_note_in_collection) => {
-            get_update_action(pankit_db, anki_note, anki_note_in_collection)
+            get_update_action(pankit_db, pundit_note, anki_note, anki_note_in_collection)
         }
     }
 }
 
 fn get_update_action<'a>(
     pankit_db: &PankitDatabase,
+    pundit_note: &'a Note,
     anki_note_pundit: &'a AnkiNote,
     anki_note_anki: &'a AnkiNote,
 ) -> Action<'a> {
-    let anki_csum = get_csum(&anki_note_anki.flds);
-    let pundit_csum = get_csum(&anki_note_pundit.flds);
+    let previous_tags = get_previous_tags(pankit_db, anki_note_pundit.id);
+    let anki_csum = get_synced_csum(
+        &anki_note_anki.flds,
+        &get_managed_anki_tags(anki_note_anki, anki_note_pundit, previous_tags),
+    );
+    let pundit_csum = get_synced_csum(&anki_note_pundit.flds, &get_tag_set(&anki_note_pundit.tags));
     if anki_csum == pundit_csum {
         // Everything up to date between anki and pundit - simply update the database.
-        Action::ChangeInDatabase(anki_note_anki)
+        Action::ChangeInDatabase(anki_note_anki, anki_note_pundit)
     } else {
         match pankit_db.get(&anki_note_pundit.id) {
             None => {
@@ -243,6 +574,7 @@ fn get_update_action<'a>(
                 Action::AskUserConflict(NoteConflict {
                     anki: anki_note_anki,
                     pundit: anki_note_pundit,
+                    pundit_note,
                 })
             }
             Some(entry) => {
@@ -253,17 +585,24 @@ fn get_update_action<'a>(
                     // Anki contents differ from the pundit/pankit contents. This could be for one of two reasons:
                     // 1. The anki note was changed (most likely). In this case we want to pull the changes from anki
                     // 2. In a previous run, we succesfully updated the pankit database but failed to update the
-                    // anki database for some reason. In this case we want to push the changes to anki
+                    // anki database for some reason. Since the collection is now committed before the pankit
+                    // database is written, this only happens for databases written by older versions.
+                    // In this case we want to push the changes to anki
                     // To check which is the case, we compare the modification times in the pankit database and anki
                     // In the first case, the anki timestamp is later than the pankit timestamp
                     // In the second case, they are equal.
                     // If the anki timestamp is later than the pankit timestamp, something strange happened. We'll ask the user what to do
                     match anki_note_anki.mod_.cmp(&entry.mod_) {
-                        Greater => Action::ChangeInDatabaseAndPundit(anki_note_anki),
+                        Greater => Action::ChangeInDatabaseAndPundit(
+                            anki_note_anki,
+                            anki_note_pundit,
+                            pundit_note,
+                        ),
                         Equal => Action::ChangeInDatabaseAndAnki(anki_note_pundit),
                         Less => Action::AskUserConflict(NoteConflict {
                             anki: anki_note_anki,
                             pundit: anki_note_pundit,
+                            pundit_note,
                         }),
                     }
                 } else {
@@ -271,6 +610,7 @@ fn get_update_action<'a>(
                     Action::AskUserConflict(NoteConflict {
                         anki: anki_note_anki,
                         pundit: anki_note_pundit,
+                        pundit_note,
                     })
                 }
             }
@@ -278,41 +618,197 @@ fn get_update_action<'a>(
     }
 }
 
-fn update_database_entry(pankit_db: &mut PankitDatabase, anki_note: &AnkiNote) {
+fn update_database_entry(pankit_db: &mut PankitDatabase, anki_note: &AnkiNote, managed_tags: &str) {
+    let tags = get_tag_set(managed_tags);
     pankit_db.insert(
         anki_note.id,
         PankitNoteInfo {
-            csum: get_csum(&anki_note.flds),
+            csum: get_synced_csum(&anki_note.flds, &tags),
             mod_: anki_note.mod_,
+            tags: tags.into_iter().collect(),
         },
     );
 }
 
-pub fn get_anki_notes_and_cards_for_pundit_notes(
+/// Replace the tags pankit wrote into the anki note during the last synchronization by the
+/// current tags from pundit. Tags which were added in anki are kept.
+fn update_managed_tags(
+    connection: &Connection,
+    pankit_db: &PankitDatabase,
+    anki_note_pundit: &AnkiNote,
+    mod_: i64,
+) -> Result<()> {
+    let anki_tags = read_anki_note_tags(connection, anki_note_pundit.id).context(format!(
+        "While reading tags of note {}",
+        anki_note_pundit.id
+    ))?;
+    let tags = get_merged_tags(
+        &anki_tags,
+        get_previous_tags(pankit_db, anki_note_pundit.id),
+        &anki_note_pundit.tags,
+    );
+    if tags != get_tag_set(&anki_tags) {
+        let tags: Vec<String> = tags.into_iter().collect();
+        update_anki_note_tags(
+            connection,
+            anki_note_pundit.id,
+            &format_anki_tags(&tags),
+            mod_,
+        )?;
+    }
+    Ok(())
+}
+
+/// The tags pankit wrote into the anki note during the last synchronization.
+fn get_previous_tags(pankit_db: &PankitDatabase, id: PankitId) -> &[String] {
+    pankit_db
+        .get(&id)
+        .map(|entry| entry.tags.as_slice())
+        .unwrap_or(&[])
+}
+
+fn get_tag_set(tags: &str) -> BTreeSet<String> {
+    tags.split_whitespace().map(|tag| tag.to_owned()).collect()
+}
+
+fn join_tags(tags: BTreeSet<String>) -> String {
+    tags.into_iter().collect::<Vec<String>>().join(" ")
+}
+
+/// The tags of the anki note which are managed by pankit: The tags from pundit and the tags
+/// written during the last synchronization. All other tags in anki are left alone.
+fn get_managed_anki_tags(
+    anki_note_anki: &AnkiNote,
+    anki_note_pundit: &AnkiNote,
+    previous_tags: &[String],
+) -> BTreeSet<String> {
+    let pundit_tags = get_tag_set(&anki_note_pundit.tags);
+    get_tag_set(&anki_note_anki.tags)
+        .into_iter()
+        .filter(|tag| pundit_tags.contains(tag) || previous_tags.contains(tag))
+        .collect()
+}
+
+/// The tags of the anki note after replacing the managed tags by the tags from pundit.
+fn get_merged_tags(
+    anki_tags: &str,
+    previous_tags: &[String],
+    pundit_tags: &str,
+) -> BTreeSet<String> {
+    get_tag_set(anki_tags)
+        .into_iter()
+        .filter(|tag| !previous_tags.contains(tag))
+        .chain(get_tag_set(pundit_tags))
+        .collect()
+}
+
+/// The checksum of the fields and the tags managed by pankit. The tags are only included if there
+/// are any, so that the entries of pankit databases written before tags were synchronized stay valid.
+fn get_synced_csum(flds: &str, managed_tags: &BTreeSet<String>) -> i64 {
+    match managed_tags.is_empty() {
+        true => get_csum(flds),
+        false => get_csum(&format!(
+            "{}{}{}",
+            flds,
+            ANKI_FIELD_SEPARATOR,
+            join_tags(managed_tags.clone())
+        )),
+    }
+}
+
+/// The anki notes and cards defined in the pankit blocks of the pundit notes, together with the pundit note containing them.
+pub fn get_anki_notes_and_cards_for_pundit_notes<'a>(
     collection: &AnkiCollection,
-    notes: &Notes,
-) -> Result<Vec<(AnkiNote, Vec<AnkiCard>)>> {
+    notes: &'a Notes,
+    tag_sources: &[TagSource],
+) -> Result<Vec<(&'a Note, AnkiNote, Vec<AnkiCard>)>> {
     let mut results = vec![];
     for pundit_note in notes.iter() {
-        results.extend(get_anki_notes_and_cards_for_pundit_note(
-            collection,
-            pundit_note,
-        )?)
+        let derived_tags = get_derived_tags(&notes.folder, pundit_note, tag_sources)?;
+        results.extend(
+            get_anki_notes_and_cards_for_pundit_note(collection, pundit_note, &derived_tags)?
+                .into_iter()
+                .map(|(anki_note, anki_cards)| (pundit_note, anki_note, anki_cards)),
+        )
     }
     Ok(results)
 }
 
+/// Write the field contents of the anki note into the pankit block of the pundit note.
+fn update_pundit_note_contents(
+    collection: &AnkiCollection,
+    pundit_writes: &mut PunditWrites,
+    anki_note: &AnkiNote,
+    pundit_note: &Note,
+) -> Result<()> {
+    info!(
+        "Updating note contents {} in {}",
+        anki_note.id,
+        pundit_note.filename.to_str().unwrap()
+    );
+    let model = collection
+        .models
+        .iter()
+        .find(|model| model.id == anki_note.mid)
+        .ok_or_else(|| anyhow!("No model with id {} in collection", anki_note.mid))?;
+    let fields: HashMap<String, String> = model
+        .flds
+        .iter()
+        .zip(anki_note.flds.split(ANKI_FIELD_SEPARATOR))
+        .filter(|(field, _)| !is_note_id_field(&field.name))
+        .map(|(field, value)| (field.name.clone(), value.to_owned()))
+        .collect();
+    let contents = pundit_writes.get_contents(pundit_note)?;
+    let new_contents = replace_field_values(&contents, anki_note.id, &fields).context(format!(
+        "While writing anki note {} into {:?}",
+        anki_note.id, pundit_note.filename
+    ))?;
+    pundit_writes.set_contents(pundit_note, new_contents);
+    Ok(())
+}
+
+/// The tags which all anki notes in the pundit note get in addition to the tags in their pankit block.
+fn get_derived_tags(
+    notes_folder: &Path,
+    pundit_note: &Note,
+    tag_sources: &[TagSource],
+) -> Result<Vec<String>> {
+    let mut tags = vec![];
+    for tag_source in tag_sources.iter() {
+        match tag_source {
+            TagSource::Title => tags.push(pundit_note.title.clone()),
+            TagSource::Folder => {
+                let folder =
+                    get_relative_path(pundit_note.filename.parent().unwrap(), notes_folder)?;
+                let components: Vec<&str> = folder
+                    .components()
+                    .map(|component| component.as_os_str().to_str().unwrap())
+                    .collect();
+                if !components.is_empty() {
+                    tags.push(components.join("::"));
+                }
+            }
+            TagSource::Filetags => tags.extend(pundit_note.tags.iter().cloned()),
+        }
+    }
+    Ok(tags)
+}
+
 fn get_anki_notes_and_cards_for_pundit_note(
     collection: &AnkiCollection,
     pundit_note: &Note,
+    derived_tags: &[String],
 ) -> Result<Vec<(AnkiNote, Vec<AnkiCard>)>> {
     get_anki_info_for_pundit_note(pundit_note)
         .context(format!(
             "While reading anki entries from note {}",
             pundit_note.title
         ))?
-        .iter()
-        .map(|anki_note_info| get_new_anki_note_and_cards(collection, anki_note_info))
+        .into_iter()
+        .map(|mut anki_note_info| {
+            anki_note_info.tags.extend(derived_tags.iter().cloned());
+            get_new_anki_note_and_cards(collection, &anki_note_info)
+        })
         .collect::<Result<Vec<(AnkiNote, Vec<AnkiCard>)>>>()
         .context(format!(
             "While making new anki cards out of note {}",
@@ -343,13 +839,10 @@ fn get_anki_info_from_note_contents(contents: String) -> Result<Vec<AnkiNoteInfo
 }
 
 fn get_anki_block_regex() -> Regex {
-    Regex::new(r"\#\+(?mis)begin_src *yaml *\n(.*)(?i)\#\+end_src").unwrap()
+    Regex::new(r"\#\+(?mis)begin_src *yaml *\n(.*?)(?i)\#\+end_src").unwrap()
 }
 
-pub fn pankit_get_note(
-    database: &std::path::PathBuf,
-    model_filename: Option<PathBuf>,
-) -> Result<()> {
+pub fn pankit_get_note(database: &Path, model_filename: Option<PathBuf>) -> Result<()> {
     let connection = Connection::open(database).unwrap();
     let collection = read_collection(&connection)?;
     close_connection(connection)?;
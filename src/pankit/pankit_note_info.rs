@@ -9,6 +9,9 @@ pub type PankitId = i64;
 pub struct PankitNoteInfo {
     pub csum: i64,
     pub mod_: i64,
+    /// The tags pankit wrote into the anki note during the last synchronization
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 pub type PankitDatabase = HashMap<PankitId, PankitNoteInfo>;
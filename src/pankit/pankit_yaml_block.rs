@@ -8,18 +8,22 @@ use serde::Serialize;
 use super::pankit_yaml_note::PankitYamlNote;
 use crate::anki::AnkiNoteInfo;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct PankitYamlBlock {
     #[serde(flatten)]
     pub notes: HashMap<String, PankitYamlNote>,
     pub model: Option<String>,
     pub deck: Option<String>,
+    /// Tags of all notes in the block
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl PankitYamlBlock {
     pub fn into_notes(self) -> Result<Vec<AnkiNoteInfo>> {
         let default_model = self.model.clone();
         let default_deck = self.deck.clone();
+        let block_tags = self.tags;
         self.notes
             .into_iter()
             .map(|(id_string, note)| {
@@ -34,6 +38,7 @@ impl PankitYamlBlock {
                         .deck
                         .or(default_deck.clone())
                         .ok_or_else(|| anyhow!("No deck specified for card."))?,
+                    tags: block_tags.iter().cloned().chain(note.tags).collect(),
                 })
             })
             .collect()
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+
+use super::get_anki_block_regex;
+use super::pankit_yaml_block::PankitYamlBlock;
+use super::pankit_yaml_note::PankitYamlNote;
+
+/// Replace the values of the fields of the pankit note with the given id in the contents of a pundit note.
+/// Only the lines of fields whose values changed are rewritten, so that the surrounding contents
+/// and the formatting of the block are kept intact.
+pub fn replace_field_values(
+    contents: &str,
+    id: i64,
+    fields: &HashMap<String, String>,
+) -> Result<String> {
+    let (block_start, mut block_end) = find_block_lines(contents, id)?
+        .ok_or_else(|| anyhow!("No pankit block containing note {} found", id))?;
+    let mut lines: Vec<String> = contents.lines().map(|line| line.to_owned()).collect();
+    let current_fields = read_note(&lines[block_start..block_end], id)?.fields;
+    let mut field_names: Vec<&String> = fields.keys().collect();
+    field_names.sort();
+    for name in field_names {
+        let value = &fields[name];
+        if current_fields.get(name) == Some(value) {
+            continue;
+        }
+        let (start, end, indentation) = find_field_lines(&lines[block_start..block_end], id, name)?;
+        let field_lines = format_field(&indentation, name, value)?;
+        block_end = block_end - (end - start) + field_lines.len();
+        lines.splice(block_start + start..block_start + end, field_lines);
+    }
+    let note = read_note(&lines[block_start..block_end], id)?;
+    if &note.fields != fields {
+        return Err(anyhow!(
+            "Fields of note {} differ from the anki note after replacing them",
+            id
+        ));
+    }
+    let mut result = lines.join("\n");
+    if contents.ends_with('\n') {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+/// The first and last line (exclusive) of the contents of the pankit block which contains the note.
+fn find_block_lines(contents: &str, id: i64) -> Result<Option<(usize, usize)>> {
+    for capture in get_anki_block_regex().captures_iter(contents) {
+        let block = capture.get(1).unwrap();
+        let start = contents[..block.start()].lines().count();
+        let end = start + block.as_str().lines().count();
+        let lines: Vec<String> = block.as_str().lines().map(|line| line.to_owned()).collect();
+        if find_note_line(&lines, id).is_some() {
+            return Ok(Some((start, end)));
+        }
+    }
+    Ok(None)
+}
+
+fn read_note(block_lines: &[String], id: i64) -> Result<PankitYamlNote> {
+    let mut block: PankitYamlBlock = serde_yaml::from_str(&block_lines.join("\n"))
+        .context(format!("While reading pankit block of note {}", id))?;
+    block
+        .notes
+        .remove(&id.to_string())
+        .ok_or_else(|| anyhow!("No note {} found in pankit block", id))
+}
+
+fn find_note_line(block_lines: &[String], id: i64) -> Option<usize> {
+    let key = format!("{}:", id);
+    block_lines
+        .iter()
+        .position(|line| line.trim() == key && get_indentation(line).is_empty())
+}
+
+/// The first and last line (exclusive) of the entry of the field and its indentation.
+fn find_field_lines(block_lines: &[String], id: i64, name: &str) -> Result<(usize, usize, String)> {
+    let note_line = find_note_line(block_lines, id)
+        .ok_or_else(|| anyhow!("No note {} found in pankit block", id))?;
+    let note_end = get_entry_end(block_lines, note_line, "");
+    let key = format!("{}:", name);
+    let field_line = (note_line + 1..note_end)
+        .find(|i| {
+            let line = &block_lines[*i];
+            line.trim_start().starts_with(&key)
+                && !get_indentation(line).is_empty()
+                && line[get_indentation(line).len() + key.len()..]
+                    .chars()
+                    .next()
+                    .map(|c| c.is_whitespace())
+                    .unwrap_or(true)
+        })
+        .ok_or_else(|| anyhow!("No field {} found for note {}", name, id))?;
+    let indentation = get_indentation(&block_lines[field_line]);
+    let mut field_end = get_entry_end(block_lines, field_line, indentation);
+    // Keep empty lines between fields.
+    while field_end > field_line + 1 && block_lines[field_end - 1].trim().is_empty() {
+        field_end -= 1;
+    }
+    Ok((field_line, field_end.min(note_end), indentation.to_owned()))
+}
+
+/// The line after the last line belonging to the entry starting at the given line,
+/// which is the first non-empty line that is not indented further than the entry.
+fn get_entry_end(block_lines: &[String], line: usize, indentation: &str) -> usize {
+    (line + 1..block_lines.len())
+        .find(|i| {
+            let line = &block_lines[*i];
+            !line.trim().is_empty() && get_indentation(line).len() <= indentation.len()
+        })
+        .unwrap_or(block_lines.len())
+}
+
+fn get_indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Write the field as a plain value if possible, as a literal block for multi-line values
+/// or as a quoted string if nothing else reproduces the value exactly.
+fn format_field(indentation: &str, name: &str, value: &str) -> Result<Vec<String>> {
+    let block_indentation = format!("{}    ", indentation);
+    let chomping = match value.len() - value.trim_end_matches('\n').len() {
+        0 => "-",
+        1 => "",
+        _ => "+",
+    };
+    let mut literal_block = vec![format!("{}{}: |{}", indentation, name, chomping)];
+    literal_block.extend(value.lines().map(|line| match line.is_empty() {
+        true => "".to_owned(),
+        false => format!("{}{}", block_indentation, line),
+    }));
+    let candidates = vec![
+        vec![format!("{}{}: {}", indentation, name, value)],
+        literal_block,
+        vec![format!(
+            "{}{}: {}",
+            indentation,
+            name,
+            serde_json::to_string(value)?
+        )],
+    ];
+    candidates
+        .into_iter()
+        .find(|lines| reads_as(lines, indentation, name, value))
+        .ok_or_else(|| anyhow!("Unable to write value of field {}", name))
+}
+
+fn reads_as(lines: &[String], indentation: &str, name: &str, value: &str) -> bool {
+    let unindented: Vec<&str> = lines
+        .iter()
+        .map(|line| line.strip_prefix(indentation).unwrap_or(line))
+        .collect();
+    serde_yaml::from_str::<HashMap<String, String>>(&unindented.join("\n"))
+        .map(|fields| fields.get(name).map(|read| read == value).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+/// Remove the entry of the pankit note with the given id from the contents of a pundit note.
+/// If no other notes are left in the block, the whole block is removed.
+pub fn remove_note(contents: &str, id: i64) -> Result<String> {
+    let (block_start, block_end) = find_block_lines(contents, id)?
+        .ok_or_else(|| anyhow!("No pankit block containing note {} found", id))?;
+    let mut lines: Vec<String> = contents.lines().map(|line| line.to_owned()).collect();
+    let block_lines = &lines[block_start..block_end];
+    let note_line = find_note_line(block_lines, id).unwrap();
+    let note_end = get_entry_end(block_lines, note_line, "");
+    let mut remaining_lines = block_lines.to_vec();
+    remaining_lines.drain(note_line..note_end);
+    let block: PankitYamlBlock = match remaining_lines.iter().all(|line| line.trim().is_empty()) {
+        true => PankitYamlBlock::default(),
+        false => serde_yaml::from_str(&remaining_lines.join("\n")).context(format!(
+            "While reading pankit block after removing note {}",
+            id
+        ))?,
+    };
+    match block.notes.is_empty() {
+        // Remove the begin_src and end_src lines as well.
+        true => lines.drain(block_start - 1..block_end + 1),
+        false => lines.drain(block_start + note_line..block_start + note_end),
+    };
+    let mut result = lines.join("\n");
+    if contents.ends_with('\n') {
+        result.push('\n');
+    }
+    Ok(result)
+}
@@ -9,4 +9,7 @@ pub struct PankitYamlNote {
     pub fields: HashMap<String, String>,
     pub deck: Option<String>,
     pub model: Option<String>,
+    /// Tags in addition to the tags of the block
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -8,19 +9,41 @@ use anyhow::Result;
 use log::info;
 use regex::Regex;
 
-use crate::config;
 use crate::fzf::select_interactively;
 use crate::named::Named;
 use crate::note::Note;
+use crate::note_format::NoteFormat;
 use crate::note_utils::find_or_create_note_with_special_content;
 use crate::notes::Notes;
 use crate::paper_opts::PaperOpts;
 use crate::paper_opts::PaperSubCommand;
+use crate::settings::PaperSettings;
+use crate::template::get_new_note_content;
+use crate::template::Template;
+use crate::template::TemplateValues;
+use crate::template::Templates;
 
-pub fn run_paper(notes: &mut Notes, args: &PaperOpts) -> Result<()> {
-    let bibtex_file = args.bibtex_file.canonicalize()?;
+pub fn run_paper(
+    notes: &mut Notes,
+    args: &PaperOpts,
+    templates: &Templates,
+    settings: &PaperSettings,
+) -> Result<()> {
+    let bibtex_file = args
+        .bibtex_file
+        .as_ref()
+        .ok_or_else(|| {
+            anyhow!("No bibtex file specified. Pass it as an argument or select a profile containing it.")
+        })?
+        .canonicalize()?;
     match &args.subcmd {
-        PaperSubCommand::Find => find_paper_note(notes, &bibtex_file),
+        PaperSubCommand::Find => find_paper_note(
+            notes,
+            &bibtex_file,
+            templates,
+            args.template.as_deref(),
+            settings,
+        ),
         PaperSubCommand::List => list_papers(&bibtex_file),
     }
 }
@@ -33,20 +56,40 @@ fn list_papers(bibtex_file: &Path) -> Result<()> {
     Ok(())
 }
 
-fn find_paper_note(notes: &mut Notes, bibtex_file: &Path) -> Result<()> {
-    let citekeys = get_citekeys_from_file(bibtex_file)?;
+fn find_paper_note(
+    notes: &mut Notes,
+    bibtex_file: &Path,
+    templates: &Templates,
+    template_name: Option<&str>,
+    settings: &PaperSettings,
+) -> Result<()> {
+    let contents = read_bibtex_file(bibtex_file)?;
+    let citekeys = get_citekeys_from_contents(&contents);
     let selected_citekey = select_interactively(&citekeys);
     if let Some(selected_citekey) = selected_citekey {
-        let note = find_note_for_cite_key(notes, selected_citekey)?;
+        let paper_folder = get_paper_folder(notes, settings)?;
+        let template = templates.find(&notes.folder, &paper_folder, template_name)?;
+        let mut values = TemplateValues::today(selected_citekey);
+        values.citekey = Some(selected_citekey.clone());
+        values.bibtex_fields = get_bibtex_fields(&contents, selected_citekey);
+        let note = find_note_for_cite_key(
+            notes,
+            selected_citekey,
+            template.as_ref(),
+            &values,
+            settings,
+        )?;
         note.show_filename();
     };
     Ok(())
 }
 
 fn get_citekeys_from_file(file: &Path) -> Result<Vec<String>> {
-    let file_contents =
-        fs::read_to_string(file).context(format!("While reading bibtex file at {:?}", &file))?;
-    Ok(get_citekeys_from_contents(&file_contents))
+    Ok(get_citekeys_from_contents(&read_bibtex_file(file)?))
+}
+
+fn read_bibtex_file(file: &Path) -> Result<String> {
+    fs::read_to_string(file).context(format!("While reading bibtex file at {:?}", &file))
 }
 
 fn get_citekeys_from_contents(contents: &str) -> Vec<String> {
@@ -57,31 +100,106 @@ fn get_citekeys_from_contents(contents: &str) -> Vec<String> {
         .collect()
 }
 
-fn find_note_for_cite_key<'a>(notes: &'a mut Notes, citekey: &str) -> Result<&'a Note> {
-    create_new_paper_note_from_title(notes, citekey)
+/// The fields of the bibtex entry with the citekey, with lowercase field names. Braces or
+/// quotes around the values are removed and whitespace is collapsed.
+fn get_bibtex_fields(contents: &str, citekey: &str) -> HashMap<String, String> {
+    let re = Regex::new(&format!(r"@\w+\{{\s*{}\s*,", regex::escape(citekey))).unwrap();
+    let mut fields = HashMap::new();
+    let mut rest = match re.find(contents) {
+        Some(m) => &contents[m.end()..],
+        None => return fields,
+    };
+    while let Some((name, after_name)) = rest.split_once('=') {
+        let name = name.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if name.starts_with('}') {
+            break;
+        }
+        let (value, after_value) = split_bibtex_value(after_name.trim_start());
+        let value = value.split_whitespace().collect::<Vec<&str>>().join(" ");
+        fields.insert(name.trim().to_lowercase(), value);
+        rest = after_value;
+        if rest.trim_start().starts_with('}') {
+            break;
+        }
+    }
+    fields
 }
 
-fn get_paper_base_note(notes: &Notes) -> Result<&Note> {
-    notes.find_by_title(config::PAPER_NOTE_TITLE).ok_or_else(|| anyhow!("No note with title '{}' found in notes. Create one so that the new paper note can link to it.", config::PAPER_NOTE_TITLE))
+/// Split off a bibtex value which is either enclosed in (possibly nested) braces,
+/// in quotes or ends at the next comma or closing brace.
+fn split_bibtex_value(text: &str) -> (&str, &str) {
+    if let Some(quoted) = text.strip_prefix('"') {
+        return match quoted.find('"') {
+            Some(end) => (&quoted[..end], &quoted[end + 1..]),
+            None => (quoted, ""),
+        };
+    }
+    if let Some(braced) = text.strip_prefix('{') {
+        let mut depth = 1;
+        for (i, c) in braced.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return (&braced[..i], &braced[i + 1..]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        return (braced, "");
+    }
+    match text.find([',', '}']) {
+        Some(end) => (text[..end].trim(), &text[end..]),
+        None => (text.trim(), ""),
+    }
 }
 
-fn get_paper_folder(notes: &Notes) -> Result<PathBuf> {
-    let paper_folder = notes.folder.join(config::PAPER_FOLDER_NAME);
+fn find_note_for_cite_key<'a>(
+    notes: &'a mut Notes,
+    citekey: &str,
+    template: Option<&Template>,
+    values: &TemplateValues,
+    settings: &PaperSettings,
+) -> Result<&'a Note> {
+    create_new_paper_note_from_title(notes, citekey, template, values, settings)
+}
+
+fn get_paper_base_note<'a>(notes: &'a Notes, settings: &PaperSettings) -> Result<&'a Note> {
+    notes.find_by_title(&settings.note_title).ok_or_else(|| anyhow!("No note with title '{}' found in notes. Create one so that the new paper note can link to it.", settings.note_title))
+}
+
+fn get_paper_folder(notes: &Notes, settings: &PaperSettings) -> Result<PathBuf> {
+    let paper_folder = notes.folder.join(&settings.folder_name);
     if !paper_folder.is_dir() {
         return Err(anyhow!(
             "No folder '{}' found in notes folder.",
-            config::PAPER_FOLDER_NAME
+            settings.folder_name
         ));
     }
     Ok(paper_folder)
 }
 
-fn create_new_paper_note_from_title<'a>(notes: &'a mut Notes, citekey: &str) -> Result<&'a Note> {
-    let paper_note = get_paper_base_note(notes)?;
-    let paper_folder = get_paper_folder(notes)?;
+fn create_new_paper_note_from_title<'a>(
+    notes: &'a mut Notes,
+    citekey: &str,
+    template: Option<&Template>,
+    values: &TemplateValues,
+    settings: &PaperSettings,
+) -> Result<&'a Note> {
+    let paper_note = get_paper_base_note(notes, settings)?;
+    let paper_folder = get_paper_folder(notes, settings)?;
     let cite_string = format!("cite:{}", citekey);
-    let link_text = paper_note.get_link_from_folder(&paper_folder)?;
-    let additional_content = format!("\n{}\n{}", &link_text, cite_string);
+    let link_text =
+        paper_note.get_link_from_folder(&paper_folder, NoteFormat::default(), &notes.settings)?;
+    let additional_content = get_new_note_content(
+        notes,
+        &paper_folder,
+        template,
+        values,
+        &format!("\n{}\n{}", &link_text, cite_string),
+    )?;
     let title = citekey;
     let target_note = find_or_create_note_with_special_content(
         notes,
@@ -5,7 +5,11 @@ use clap::Parser;
 /// Create and find note files for papers from a bibtex file
 #[derive(Parser, Debug)]
 pub struct PaperOpts {
-    pub bibtex_file: PathBuf,
+    /// The bibtex file containing the papers. Can be left out if the profile contains it.
+    pub bibtex_file: Option<PathBuf>,
+    /// The name of the template for new paper notes. By default, the template for the paper folder is used, if there is one.
+    #[clap(long)]
+    pub template: Option<String>,
     #[clap(subcommand)]
     pub subcmd: PaperSubCommand,
 }
@@ -2,44 +2,140 @@ pub mod logger;
 
 use std::error::Error;
 use std::path::Path;
+use std::path::PathBuf;
 
 use anyhow::anyhow;
+use anyhow::Context;
 use anyhow::Result;
 use clap::Parser;
-use log::error;
 use log::info;
 use logger::init_logger;
+use pundit::args::NeighbourhoodOptions;
 use pundit::args::Opts;
 use pundit::args::SubCommand;
+use pundit::check::run_check;
+use pundit::config_command::run_config;
+use pundit::config_opts::ConfigOpts;
+use pundit::config_opts::ConfigSubCommand;
+use pundit::delete::delete_note;
+use pundit::file_utils::append_to_file;
+use pundit::filter_options::get_num_backlinks;
 use pundit::filter_options::FilterOptions;
+use pundit::fix_links::fix_links;
 use pundit::fzf::run_fzf;
 use pundit::graph::get_connected_component_undirected;
+use pundit::graph::get_distances;
+use pundit::graph::get_neighbourhood;
+use pundit::graph::get_shortest_path;
+use pundit::graph::Direction;
+use pundit::graph_export::export_graph;
 use pundit::note::create_new_note_from_title;
 use pundit::note::Note;
 use pundit::note_utils::get_backlinks;
 use pundit::notes::read_notes;
 use pundit::notes::Notes;
+use pundit::pankit::SyncPolicy;
+use pundit::rename::rename_note;
+use pundit::search::run_search;
+use pundit::search::SearchQuery;
+use pundit::settings::NoteSettings;
+use pundit::settings::Profile;
 use pundit::settings::Settings;
+use pundit::settings::SettingsLocation;
+use pundit::stats::show_stats;
+use pundit::suggest_links::suggest_links;
+use pundit::tags::edit_tags;
+use pundit::tags::get_all_tags;
+use pundit::template::get_new_note_content;
+use pundit::template::TemplateValues;
+use pundit::template::Templates;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut args = get_args();
-    let settings = Settings::from_default_location();
-    if let Some(mut settings) = settings {
-        settings.expand_all_paths()?;
-        update_args_with_settings(&mut args, &settings);
-    }
+    let location = SettingsLocation::find(args.config.as_deref());
+    let strict = args.strict_config
+        || matches!(
+            &args.subcmd,
+            SubCommand::Config(ConfigOpts {
+                subcmd: ConfigSubCommand::Validate
+            })
+        );
     init_logger(args.add_identifier).unwrap();
-    let note_folder = args.folder.as_ref().unwrap().canonicalize()?;
-    let mut notes = read_notes(&note_folder, &args.database, !args.singledir)?;
-    run(args, &mut notes)?;
+    let settings = Settings::read(&location, strict).map_err(anyhow::Error::from)?;
+    update_args_with_settings(&mut args, &settings)?;
+    if let SubCommand::Config(l) = &args.subcmd {
+        run_config(l, &args, &settings, &location)?;
+        return Ok(());
+    }
+    let note_folder = args
+        .folder
+        .as_ref()
+        .ok_or_else(|| {
+            anyhow!(
+                "No pundit folder specified. Pass it as an argument, select a profile or set pundit_folder in the settings."
+            )
+        })?;
+    let note_folder = note_folder
+        .canonicalize()
+        .context(format!("While reading note folder {:?}", note_folder))?;
+    // These have to read the files leniently, so they cannot use the regular notes.
+    if let SubCommand::Check(l) = &args.subcmd {
+        run_check(&note_folder, !args.singledir, &l.output, &settings.note)?;
+        return Ok(());
+    }
+    if let SubCommand::FixLinks(l) = &args.subcmd {
+        fix_links(&note_folder, !args.singledir, l.dry_run, &settings.note)?;
+        return Ok(());
+    }
+    let mut notes = read_notes(
+        &note_folder,
+        &args.database,
+        !args.singledir,
+        &settings.note,
+    )?;
+    run(args, &mut notes, &settings)?;
     Ok(())
 }
 
-fn update_args_with_settings(args: &mut Opts, settings: &Settings) {
-    args.folder = args.folder.clone().or(settings.pundit_folder.clone());
-    if args.folder.is_none() {
-        panic!("No pundit folder specified!");
+fn update_args_with_settings(args: &mut Opts, settings: &Settings) -> Result<()> {
+    let profile = match &args.profile {
+        Some(name) => settings.get_profile(name)?.clone(),
+        None => Profile::default(),
+    };
+    args.folder = args
+        .folder
+        .clone()
+        .or(profile.folder)
+        .or(settings.pundit_folder.clone());
+    args.database = args.database.clone().or(profile.database);
+    args.template_folder = args
+        .template_folder
+        .clone()
+        .or(settings.template_folder.clone());
+    match &mut args.subcmd {
+        SubCommand::Pankit(l) => {
+            l.database = l.database.take().or(profile.anki_database);
+            l.pankit_db = l.pankit_db.take().or(profile.pankit_database);
+        }
+        SubCommand::PankitGetNote(l) => {
+            l.database = l.database.take().or(profile.anki_database);
+        }
+        SubCommand::Paper(l) => {
+            l.bibtex_file = l.bibtex_file.take().or(profile.bibtex_file);
+        }
+        _ => {}
     }
+    Ok(())
+}
+
+/// The path given as an argument or in the profile.
+fn get_required_path<'a>(path: &'a Option<PathBuf>, name: &str) -> Result<&'a Path> {
+    path.as_deref().ok_or_else(|| {
+        anyhow!(
+            "No {} specified. Pass it as an argument or select a profile containing it.",
+            name
+        )
+    })
 }
 
 fn get_notes<'a>(
@@ -58,7 +154,7 @@ fn get_notes_filtered<'a>(
 ) -> impl Iterator<Item = &'a Note> {
     notes
         .iter()
-        .filter(move |note| filter.includes_note(&notes.folder, note))
+        .filter(move |note| filter.includes_note(notes, note))
 }
 
 fn list_notes(notes: &Notes, filter: Option<FilterOptions>) {
@@ -80,17 +176,25 @@ fn list_backlinks(notes: &Notes, note: &Note, show_path: bool) {
 fn find_backlinked_note_interactively(notes: &Notes, note: &Note) -> Result<()> {
     let backlinks = get_backlinks(notes, note);
     let backlinks_coll: Vec<&Note> = backlinks.collect();
-    select_note_interactively(notes, &backlinks_coll)
+    select_note_interactively(notes, &backlinks_coll, None)
 }
 
-fn find_note_interactively(notes: &Notes, filter: Option<FilterOptions>) -> Result<()> {
+fn find_note_interactively(
+    notes: &Notes,
+    filter: Option<FilterOptions>,
+    distance_from: Option<&Note>,
+) -> Result<()> {
     let notes_filtered = get_notes(notes, filter);
     let notes_filtered_coll: Vec<&Note> = notes_filtered.collect();
-    select_note_interactively(notes, &notes_filtered_coll)
+    select_note_interactively(notes, &notes_filtered_coll, distance_from)
 }
 
-fn select_note_interactively(all_notes: &Notes, notes: &[&Note]) -> Result<()> {
-    let note = select_note_with_fzf(all_notes, notes)?;
+fn select_note_interactively(
+    all_notes: &Notes,
+    notes: &[&Note],
+    distance_from: Option<&Note>,
+) -> Result<()> {
+    let note = select_note_with_fzf(all_notes, notes, distance_from)?;
     // For interactive use from other processes: Print the filename of the resulting file.
     match note {
         Some(n) => n.show_filename(),
@@ -99,8 +203,8 @@ fn select_note_interactively(all_notes: &Notes, notes: &[&Note]) -> Result<()> {
     Ok(())
 }
 
-fn show_link(note1: &Note, note2: &Note) -> Result<()> {
-    let link_text = note2.get_link_from(note1)?;
+fn show_link(note1: &Note, note2: &Note, settings: &NoteSettings) -> Result<()> {
+    let link_text = note2.get_link_from(note1, settings)?;
     info!("{}", link_text);
     Ok(())
 }
@@ -112,16 +216,32 @@ fn show_link_interactively(
 ) -> Result<()> {
     let notes_filtered = get_notes(notes, filter);
     let notes_filtered_coll: Vec<&Note> = notes_filtered.collect();
-    let note = select_note_with_fzf(notes, &notes_filtered_coll)?;
+    let note = select_note_with_fzf(notes, &notes_filtered_coll, None)?;
     if let Some(n) = note {
-        show_link(note_src, &n)?;
+        show_link(note_src, &n, &notes.settings)?;
     }
     Ok(())
 }
 
-fn select_note_with_fzf(all_notes: &Notes, notes: &[&Note]) -> Result<Option<Note>> {
+/// Let the user select a note via fzf. The notes are sorted by title or, if `distance_from` is given,
+/// by the length of the shortest link path to that note.
+fn select_note_with_fzf(
+    all_notes: &Notes,
+    notes: &[&Note],
+    distance_from: Option<&Note>,
+) -> Result<Option<Note>> {
     let mut sorted_notes: Vec<&Note> = notes.to_vec();
     sorted_notes.sort_by(|n1, n2| n1.title.partial_cmp(&n2.title).unwrap());
+    if let Some(distance_from) = distance_from {
+        let distances = get_distances(all_notes, distance_from, Direction::Both);
+        sorted_notes.sort_by_key(|note| {
+            all_notes
+                .find_index_by_filename(&note.filename)
+                .and_then(|index| distances.get(&index))
+                .copied()
+                .unwrap_or(usize::MAX)
+        });
+    }
 
     let strs: Vec<String> = sorted_notes
         .iter()
@@ -169,34 +289,51 @@ fn run_fzf_on_notes_string(content: &str) -> String {
     run_fzf(content, args)
 }
 
-fn delete_file(filename: &Path) {
-    info!("Deleting {}", filename.to_str().unwrap());
+fn run_find_graph(
+    notes: &Notes,
+    note: &Note,
+    sort_by_distance: bool,
+    options: &NeighbourhoodOptions,
+) -> Result<()> {
+    let neighbourhood = get_neighbourhood_with_options(notes, note, options);
+    let distance_from = match sort_by_distance {
+        true => Some(note),
+        false => None,
+    };
+    select_note_interactively(notes, &neighbourhood, distance_from)
 }
 
-fn delete_note(notes: &Notes, note: &Note) {
-    let mut backlink_notes = get_backlinks(notes, note);
-    let next = backlink_notes.next();
-    match next {
-        None => delete_file(&note.filename),
-        Some(note) => {
-            error!("There are links to this note: ");
-            error!("\t{}", note.title);
-            for backlink_note in backlink_notes {
-                error!("\t{}", backlink_note.title);
-            }
-            error!("Not deleting note.");
-        }
-    }
+fn get_neighbourhood_with_options<'a>(
+    notes: &'a Notes,
+    note: &Note,
+    options: &NeighbourhoodOptions,
+) -> Vec<&'a Note> {
+    get_neighbourhood(notes, note, options.direction, options.depth, |n| {
+        options.exclude_hubs && get_num_backlinks(n) >= options.hub_min_backlinks
+    })
 }
 
-fn run_find_graph(notes: &Notes, note: &Note) -> Result<()> {
-    let connected = get_connected_component_undirected(notes, note);
-    select_note_interactively(notes, &connected)
+fn show_shortest_path(notes: &Notes, note1: &Note, note2: &Note, undirected: bool) -> Result<()> {
+    let direction = match undirected {
+        true => Direction::Both,
+        false => Direction::Out,
+    };
+    let path = get_shortest_path(notes, note1, note2, direction).ok_or_else(|| {
+        anyhow!(
+            "No link path from '{}' to '{}' found.",
+            note1.title,
+            note2.title
+        )
+    })?;
+    for note in path {
+        info!("{}", note.title);
+    }
+    Ok(())
 }
 
-fn run_list_graph(notes: &Notes, note: &Note) {
-    let connected = get_connected_component_undirected(notes, note);
-    for n in connected.iter() {
+fn run_list_graph(notes: &Notes, note: &Note, options: &NeighbourhoodOptions) {
+    let neighbourhood = get_neighbourhood_with_options(notes, note, options);
+    for n in neighbourhood.iter() {
         info!("{}", n.title);
     }
 }
@@ -212,7 +349,8 @@ fn find_by_filename<'a>(notes: &'a Notes, filename: &Path) -> Result<&'a Note> {
         .ok_or_else(|| anyhow!("Given note not found: {}", filename.to_str().unwrap()))
 }
 
-fn run(args: Opts, mut notes: &mut Notes) -> Result<()> {
+fn run(args: Opts, mut notes: &mut Notes, settings: &Settings) -> Result<()> {
+    let templates = Templates::new(args.template_folder.clone());
     match args.subcmd {
         SubCommand::List(l) => {
             list_notes(notes, l.filter);
@@ -232,43 +370,124 @@ fn run(args: Opts, mut notes: &mut Notes) -> Result<()> {
         SubCommand::ShowLink(l) => {
             let note1 = find_by_filename(notes, &l.note1)?;
             let note2 = find_by_filename(notes, &l.note2)?;
-            show_link(&note1, &note2)?;
+            show_link(&note1, &note2, &notes.settings)?;
         }
         SubCommand::New(l) => {
+            let template = templates.find(&notes.folder, &notes.folder, l.template.as_deref())?;
+            let content = get_new_note_content(
+                notes,
+                &notes.folder,
+                template.as_ref(),
+                &TemplateValues::today(&l.title),
+                "",
+            )?;
             let note = create_new_note_from_title(notes, &notes.folder, &l.title)?;
+            if !content.is_empty() {
+                append_to_file(&note.filename, &content)?;
+            }
             note.show_filename();
         }
         SubCommand::Find(l) => {
-            find_note_interactively(&notes, l.filter)?;
+            let distance_from = l
+                .distance_from
+                .map(|filename| find_by_filename(notes, &filename))
+                .transpose()?;
+            find_note_interactively(&notes, l.filter, distance_from)?;
         }
-        SubCommand::Rename(_) => {
-            todo!();
-            // let note = find_by_filename(notes, &l.filename)?;
-            // rename_note(&notes, &note, &l.new_name);
+        SubCommand::Rename(l) => {
+            let note = find_by_filename(notes, &l.filename)?;
+            rename_note(notes, note, &l.new_name, l.dry_run)?;
         }
         SubCommand::Delete(l) => {
             let note = find_by_filename(notes, &l.filename)?;
-            delete_note(&notes, &note);
+            let replacement = l
+                .replacement
+                .map(|filename| find_by_filename(notes, &filename))
+                .transpose()?;
+            delete_note(notes, note, &l.backlinks, replacement, l.trash)?;
         }
         SubCommand::Graph(l) => {
             let note = find_by_filename(notes, &l.filename)?;
-            run_find_graph(notes, note)?;
+            run_find_graph(notes, note, l.sort_by_distance, &l.neighbourhood)?;
+        }
+        SubCommand::Path(l) => {
+            let note1 = find_by_filename(notes, &l.note1)?;
+            let note2 = find_by_filename(notes, &l.note2)?;
+            show_shortest_path(notes, note1, note2, l.undirected)?;
+        }
+        SubCommand::ExportGraph(l) => {
+            let exported_notes = match l.note {
+                Some(filename) => {
+                    let note = find_by_filename(notes, &filename)?;
+                    get_connected_component_undirected(notes, note)
+                }
+                None => notes.iter().collect(),
+            };
+            export_graph(notes, &exported_notes, &l.format, l.output.as_deref())?;
+        }
+        SubCommand::Stats(l) => {
+            show_stats(notes, l.num_hubs, &l.output)?;
         }
         SubCommand::ListGraph(l) => {
             let note = find_by_filename(notes, &l.filename)?;
-            run_list_graph(notes, note);
+            run_list_graph(notes, note, &l.neighbourhood);
         }
         SubCommand::Pankit(l) => {
-            pundit::pankit::update_anki(&l.database, &l.pankit_db, &notes, l.conflict_handling)?
+            let database = get_required_path(&l.database, "anki database")?;
+            let pankit_db = get_required_path(&l.pankit_db, "pankit database")?;
+            let policy = SyncPolicy {
+                conflict_handling: l.conflict_handling,
+                removed_in_pundit: l.removed_in_pundit,
+                deleted_in_anki: l.deleted_in_anki,
+            };
+            match l.dry_run {
+                true => pundit::pankit::show_anki_update(
+                    database,
+                    pankit_db,
+                    &notes,
+                    policy,
+                    &l.derive_tags,
+                    &l.output,
+                )?,
+                false => pundit::pankit::update_anki(
+                    database,
+                    pankit_db,
+                    &notes,
+                    policy,
+                    &l.derive_tags,
+                    l.backup,
+                )?,
+            }
         }
         SubCommand::PankitGetNote(l) => {
-            pundit::pankit::pankit_get_note(&l.database, l.model_filename)?
+            let database = get_required_path(&l.database, "anki database")?;
+            pundit::pankit::pankit_get_note(database, l.model_filename)?
+        }
+        SubCommand::ListTags(l) => {
+            for tag in get_all_tags(get_notes(notes, l.filter)) {
+                info!("{}", tag);
+            }
+        }
+        SubCommand::Tag(l) => {
+            let note = find_by_filename(notes, &l.filename)?;
+            edit_tags(note, &l.add, &l.remove)?;
+        }
+        SubCommand::SuggestLinks(l) => {
+            let note = find_by_filename(notes, &l.filename)?;
+            suggest_links(notes, note, l.apply)?;
+        }
+        SubCommand::Search(l) => {
+            let query = SearchQuery::new(&l.query, l.regex)?;
+            run_search(notes, &query, args.database.as_deref(), l.interactive)?;
+        }
+        SubCommand::Check(_) | SubCommand::FixLinks(_) | SubCommand::Config(_) => {
+            unreachable!("Handled before reading the notes")
         }
         SubCommand::Journal(l) => {
-            pundit::journal::run_journal(&mut notes, &l)?;
+            pundit::journal::run_journal(&mut notes, &l, &templates, &settings.journal)?;
         }
         SubCommand::Paper(l) => {
-            pundit::paper::run_paper(&mut notes, &l)?;
+            pundit::paper::run_paper(&mut notes, &l, &templates, &settings.paper)?;
         }
     }
     Ok(())
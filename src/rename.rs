@@ -0,0 +1,86 @@
+use std::fs;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use log::info;
+
+use crate::backlink_edit::get_backlink_edits;
+use crate::backlink_edit::get_relinked_link;
+use crate::backlink_edit::FileEdit;
+use crate::note::Note;
+use crate::notes::Notes;
+
+/// Change the title of a note, move it to the corresponding filename and
+/// rewrite the links in all notes linking to it.
+pub fn rename_note(notes: &Notes, note: &Note, new_title: &str, dry_run: bool) -> Result<()> {
+    if let Some(existing) = notes.find_by_title(new_title) {
+        return Err(anyhow!(
+            "Note with title: {} already exists at {:?}",
+            new_title,
+            existing.filename
+        ));
+    }
+    let renamed_note = Note {
+        filename: note.get_filename_for_title(new_title, &notes.settings),
+        title: new_title.to_owned(),
+        links: note.links.clone(),
+        backlinks: note.backlinks.clone(),
+        tags: note.tags.clone(),
+    };
+    if renamed_note.filename.exists() {
+        return Err(anyhow!(
+            "Note with filename {:?} already exists",
+            renamed_note.filename
+        ));
+    }
+    let contents = note
+        .format()
+        .replace_title(&note.get_contents()?, new_title, &notes.settings)
+        .context(format!("While renaming {:?}", note.filename))?;
+    let backlink_edits = get_backlink_edits(notes, note, |backlink_note, description| {
+        get_relinked_link(
+            &renamed_note,
+            backlink_note,
+            &note.title,
+            description,
+            &notes.settings,
+        )
+    })?;
+    match dry_run {
+        true => show_planned_edits(note, &renamed_note, &backlink_edits),
+        false => {
+            apply_edits(note, &renamed_note, &contents, &backlink_edits)?;
+            renamed_note.show_filename();
+        }
+    }
+    Ok(())
+}
+
+fn show_planned_edits(note: &Note, renamed_note: &Note, backlink_edits: &[FileEdit]) {
+    info!("Rename '{}' to '{}'", note.title, renamed_note.title);
+    info!(
+        "Move {} to {}",
+        note.filename.to_str().unwrap(),
+        renamed_note.filename.to_str().unwrap()
+    );
+    for edit in backlink_edits {
+        edit.show();
+    }
+}
+
+fn apply_edits(
+    note: &Note,
+    renamed_note: &Note,
+    contents: &str,
+    backlink_edits: &[FileEdit],
+) -> Result<()> {
+    for edit in backlink_edits {
+        edit.write()?;
+    }
+    fs::rename(&note.filename, &renamed_note.filename)
+        .context(format!("While moving {:?}", note.filename))?;
+    fs::write(&renamed_note.filename, contents)
+        .context(format!("While writing {:?}", renamed_note.filename))?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use log::info;
+use regex::Regex;
+
+use crate::config::SEARCH_SNIPPET_LENGTH;
+use crate::fzf::run_fzf;
+use crate::note::Note;
+use crate::notes::Notes;
+use crate::search_index::get_words;
+use crate::search_index::SearchIndex;
+
+pub enum SearchQuery {
+    /// Notes containing all of the words (case-insensitive). Every line containing any of them is a match.
+    Words(Vec<String>),
+    /// Every line matching the regex is a match.
+    Regex(Regex),
+}
+
+impl SearchQuery {
+    pub fn new(query: &[String], regex: bool) -> Result<SearchQuery> {
+        let query = query.join(" ");
+        Ok(match regex {
+            true => {
+                SearchQuery::Regex(Regex::new(&query).context(format!("Invalid regex: {}", query))?)
+            }
+            false => SearchQuery::Words(get_words(&query).into_iter().collect()),
+        })
+    }
+
+    fn matches_line(&self, line: &str) -> bool {
+        match self {
+            SearchQuery::Words(words) => {
+                let line_words = get_words(line);
+                words.iter().any(|word| line_words.contains(word))
+            }
+            SearchQuery::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+pub struct SearchMatch<'a> {
+    pub note: &'a Note,
+    pub line_number: usize,
+    pub line: String,
+}
+
+impl<'a> SearchMatch<'a> {
+    pub fn get_snippet(&self) -> String {
+        self.line
+            .trim()
+            .chars()
+            .take(SEARCH_SNIPPET_LENGTH)
+            .collect()
+    }
+}
+
+/// Find all lines in the notes matching the query, ordered by note title and line number.
+/// If a database is given, word queries use the search index stored next to it.
+pub fn search_notes<'a>(
+    notes: &'a Notes,
+    query: &SearchQuery,
+    db_path: Option<&Path>,
+) -> Result<Vec<SearchMatch<'a>>> {
+    let mut candidates: Vec<&Note> = match (query, db_path) {
+        (SearchQuery::Words(words), Some(db_path)) => {
+            let index = SearchIndex::read_and_update(notes, db_path)?;
+            let files: HashSet<&PathBuf> = index.find_files_containing(words);
+            notes
+                .iter()
+                .filter(|note| files.contains(&note.filename))
+                .collect()
+        }
+        _ => notes.iter().collect(),
+    };
+    candidates.sort_by(|n1, n2| n1.title.cmp(&n2.title));
+    let mut matches = vec![];
+    for note in candidates {
+        let contents = note.get_contents()?;
+        if let SearchQuery::Words(words) = query {
+            let note_words = get_words(&contents);
+            if !words.iter().all(|word| note_words.contains(word)) {
+                continue;
+            }
+        }
+        for (line_number, line) in contents.lines().enumerate() {
+            if query.matches_line(line) {
+                matches.push(SearchMatch {
+                    note,
+                    line_number: line_number + 1,
+                    line: line.to_owned(),
+                });
+            }
+        }
+    }
+    Ok(matches)
+}
+
+/// Print all matches or, if `interactive` is set, select one of them via fzf
+/// (with a preview of the note around the matching line) and print its filename.
+pub fn run_search(
+    notes: &Notes,
+    query: &SearchQuery,
+    db_path: Option<&Path>,
+    interactive: bool,
+) -> Result<()> {
+    let matches = search_notes(notes, query, db_path)?;
+    match interactive {
+        false => {
+            for m in matches.iter() {
+                info!(
+                    "{}:{}: {}",
+                    m.note.filename.to_str().unwrap(),
+                    m.line_number,
+                    m.get_snippet()
+                );
+            }
+        }
+        true => {
+            if let Some(filename) = select_match_with_fzf(&matches) {
+                info!("{}", filename);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn select_match_with_fzf(matches: &[SearchMatch]) -> Option<String> {
+    let strs: Vec<String> = matches
+        .iter()
+        .map(|m| {
+            format!(
+                "{};{};{}: {}",
+                m.note.filename.to_str().unwrap(),
+                m.line_number,
+                m.note.title,
+                m.get_snippet()
+            )
+        })
+        .collect();
+    let args = &[
+        "--delimiter=;",
+        "--with-nth=3..",
+        "--preview=cat -n {1}",
+        "--preview-window=+{2}-5",
+    ];
+    let output = run_fzf(&strs.join("\n"), args);
+    output
+        .split(';')
+        .next()
+        .filter(|filename| !filename.is_empty())
+        .map(|filename| filename.to_owned())
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::SEARCH_INDEX_EXTENSION;
+use crate::notes::Notes;
+
+/// An inverted index from every word in the notes to the files containing it.
+#[derive(Deserialize, Serialize, Default)]
+pub struct SearchIndex {
+    words: HashMap<String, HashSet<PathBuf>>,
+    modified_times: HashMap<PathBuf, SystemTime>,
+}
+
+impl SearchIndex {
+    /// The path of the search index belonging to a notes database.
+    pub fn get_path(db_path: &Path) -> PathBuf {
+        db_path.with_extension(SEARCH_INDEX_EXTENSION)
+    }
+
+    /// Read the index stored next to the notes database and update it with all
+    /// note files that changed since the last run.
+    pub fn read_and_update(notes: &Notes, db_path: &Path) -> Result<SearchIndex> {
+        let index_path = SearchIndex::get_path(db_path);
+        let mut index = SearchIndex::from_file(&index_path).unwrap_or_default();
+        index.update(notes)?;
+        index.to_file(&index_path)?;
+        Ok(index)
+    }
+
+    fn from_file(filename: &Path) -> Result<SearchIndex> {
+        let data = fs::read_to_string(filename).context("While reading search index")?;
+        serde_json::from_str(&data).context("Reading search index contents")
+    }
+
+    fn to_file(&self, filename: &Path) -> Result<()> {
+        let data = serde_json::to_string(self).context("While converting search index to json")?;
+        fs::write(filename, data).context("Unable to write search index file")?;
+        Ok(())
+    }
+
+    fn update(&mut self, notes: &Notes) -> Result<()> {
+        let files: HashSet<&PathBuf> = notes.iter().map(|note| &note.filename).collect();
+        let mut modified_times = HashMap::new();
+        let mut changed = vec![];
+        for note in notes.iter() {
+            let modified_time = note.filename.metadata()?.modified()?;
+            if self.modified_times.get(&note.filename) != Some(&modified_time) {
+                changed.push(note);
+            }
+            modified_times.insert(note.filename.clone(), modified_time);
+        }
+        let changed_files: HashSet<&PathBuf> = changed.iter().map(|note| &note.filename).collect();
+        for containing in self.words.values_mut() {
+            containing.retain(|file| files.contains(file) && !changed_files.contains(file));
+        }
+        self.words.retain(|_, containing| !containing.is_empty());
+        for note in changed {
+            for word in get_words(&note.get_contents()?) {
+                self.words
+                    .entry(word)
+                    .or_default()
+                    .insert(note.filename.clone());
+            }
+        }
+        self.modified_times = modified_times;
+        Ok(())
+    }
+
+    /// The files which contain all of the words.
+    pub fn find_files_containing(&self, words: &[String]) -> HashSet<&PathBuf> {
+        let mut sets = words.iter().map(|word| {
+            self.words
+                .get(word)
+                .map(|files| files.iter().collect::<HashSet<_>>())
+        });
+        let first: HashSet<&PathBuf> = match sets.next() {
+            Some(Some(files)) => files,
+            _ => return HashSet::new(),
+        };
+        sets.fold(first, |result, files| match files {
+            Some(files) => result.intersection(&files).copied().collect(),
+            None => HashSet::new(),
+        })
+    }
+}
+
+/// Split a text into lowercase words.
+pub fn get_words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
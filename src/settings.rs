@@ -1,49 +1,383 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
 use std::fs::File;
+use std::io;
 use std::path::Path;
 use std::path::PathBuf;
 
-use anyhow::Context;
+use anyhow::anyhow;
 use anyhow::Result;
+use chrono::format::Item;
+use chrono::format::StrftimeItems;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
 use dirs_next::config_dir;
+use log::warn;
 use serde::Deserialize;
+use serde::Serialize;
 
-#[derive(Debug, PartialEq, Deserialize, Default)]
+use crate::config::JOURNAL_DATE_FORMAT_STR;
+use crate::config::JOURNAL_IN_SUBFOLDERS;
+use crate::config::JOURNAL_TITLE_FORMAT;
+use crate::config::LINK_FORMAT;
+use crate::config::NOTE_DATE_FORMAT_STR;
+use crate::config::NOTE_FILENAME_STR_FORMAT;
+use crate::config::PAPER_FOLDER_NAME;
+use crate::config::PAPER_NOTE_TITLE;
+use crate::config::SETTINGS_ENV_VAR;
+use crate::config::SETTINGS_FILE_NAME;
+use crate::config::TEMPLATE_FOLDER_NAME;
+use crate::config::TITLE_STRING;
+use crate::note_format::NoteFormat;
+
+#[derive(Debug, PartialEq, Deserialize, Serialize, Default)]
+#[serde(default)]
 pub struct Settings {
     pub pundit_folder: Option<PathBuf>,
+    pub template_folder: Option<PathBuf>,
+    pub note: NoteSettings,
+    pub journal: JournalSettings,
+    pub paper: PaperSettings,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// The paths belonging to one collection of notes. Selected with --profile.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct Profile {
+    /// The note folder
+    pub folder: Option<PathBuf>,
+    /// The database in which the notes and their links are stored
+    pub database: Option<PathBuf>,
+    /// The anki collection which pankit synchronizes the notes with
+    pub anki_database: Option<PathBuf>,
+    /// The pankit database used for the synchronization
+    pub pankit_database: Option<PathBuf>,
+    /// The bibtex file for paper notes
+    pub bibtex_file: Option<PathBuf>,
+}
+
+/// How the files, titles and links of notes are written.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NoteSettings {
+    /// The format of links in org notes, containing {relative_path} and {title}
+    pub link_format: String,
+    /// The prefix of the title line of org notes
+    pub title_string: String,
+    /// The filename of new notes, containing {dateString}, {titleString} and {extension}
+    pub filename_format: String,
+    /// The strftime format of the creation date in the filename
+    pub date_format: String,
+}
+
+impl Default for NoteSettings {
+    fn default() -> Self {
+        NoteSettings {
+            link_format: LINK_FORMAT.to_owned(),
+            title_string: TITLE_STRING.to_owned(),
+            filename_format: NOTE_FILENAME_STR_FORMAT.to_owned(),
+            date_format: NOTE_DATE_FORMAT_STR.to_owned(),
+        }
+    }
+}
+
+impl NoteSettings {
+    fn validate(&self) -> Result<()> {
+        for placeholder in ["{relative_path}", "{title}"] {
+            check_contains("note.link_format", &self.link_format, placeholder)?;
+        }
+        let link = self
+            .link_format
+            .replace("{relative_path}", "note.org")
+            .replace("{title}", "note");
+        if NoteFormat::Org.get_link_filenames(&link) != vec![PathBuf::from("note.org")] {
+            return Err(anyhow!(
+                "note.link_format: Links of the form '{}' are not recognized as org links",
+                self.link_format
+            ));
+        }
+        if self.title_string.trim().is_empty() {
+            return Err(anyhow!("note.title_string must not be empty"));
+        }
+        check_contains(
+            "note.filename_format",
+            &self.filename_format,
+            "{titleString}",
+        )?;
+        if !self.filename_format.ends_with(".{extension}") {
+            return Err(anyhow!(
+                "note.filename_format has to end with '.{{extension}}', got '{}'",
+                self.filename_format
+            ));
+        }
+        check_date_format("note.date_format", &self.date_format)?;
+        let date_time = NaiveDate::from_ymd_opt(2021, 2, 3)
+            .and_then(|date| date.and_hms_opt(4, 5, 6))
+            .unwrap();
+        let date_string = date_time.format(&self.date_format).to_string();
+        if date_string.contains(std::path::MAIN_SEPARATOR) {
+            return Err(anyhow!(
+                "note.date_format must not contain path separators, got '{}'",
+                self.date_format
+            ));
+        }
+        if self.parse_date(&date_string).is_none() {
+            return Err(anyhow!(
+                "note.date_format: Dates written as '{}' cannot be read back",
+                self.date_format
+            ));
+        }
+        Ok(())
+    }
+
+    /// Parse a date written in the date format. Formats without a time are read as midnight.
+    pub fn parse_date(&self, date_string: &str) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(date_string, &self.date_format)
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(date_string, &self.date_format)
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JournalSettings {
+    /// The title of journal entries, containing {date} and usually {name}
+    pub title_format: String,
+    /// The strftime format of {date}
+    pub date_format: String,
+    /// Whether each journal has its own subfolder named after the journal
+    pub in_subfolders: bool,
+}
+
+impl Default for JournalSettings {
+    fn default() -> Self {
+        JournalSettings {
+            title_format: JOURNAL_TITLE_FORMAT.to_owned(),
+            date_format: JOURNAL_DATE_FORMAT_STR.to_owned(),
+            in_subfolders: JOURNAL_IN_SUBFOLDERS,
+        }
+    }
+}
+
+impl JournalSettings {
+    fn validate(&self) -> Result<()> {
+        if self.title_format.matches("{date}").count() != 1 {
+            return Err(anyhow!(
+                "journal.title_format has to contain {{date}} exactly once, got '{}'",
+                self.title_format
+            ));
+        }
+        check_date_format("journal.date_format", &self.date_format)?;
+        let date = NaiveDate::from_ymd_opt(2021, 2, 3).unwrap();
+        let date_string = date.format(&self.date_format).to_string();
+        if NaiveDate::parse_from_str(&date_string, &self.date_format) != Ok(date) {
+            return Err(anyhow!(
+                "journal.date_format: Dates written as '{}' cannot be read back",
+                self.date_format
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PaperSettings {
+    /// The title of the note all paper notes link to
+    pub note_title: String,
+    /// The folder (relative to the note folder) containing the paper notes
+    pub folder_name: String,
+}
+
+impl Default for PaperSettings {
+    fn default() -> Self {
+        PaperSettings {
+            note_title: PAPER_NOTE_TITLE.to_owned(),
+            folder_name: PAPER_FOLDER_NAME.to_owned(),
+        }
+    }
+}
+
+impl PaperSettings {
+    fn validate(&self) -> Result<()> {
+        if self.note_title.is_empty() {
+            return Err(anyhow!("paper.note_title must not be empty"));
+        }
+        if self.folder_name.is_empty() || Path::new(&self.folder_name).is_absolute() {
+            return Err(anyhow!(
+                "paper.folder_name has to be a folder relative to the note folder, got '{}'",
+                self.folder_name
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn check_contains(name: &str, value: &str, placeholder: &str) -> Result<()> {
+    match value.contains(placeholder) {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "{} has to contain {}, got '{}'",
+            name,
+            placeholder,
+            value
+        )),
+    }
+}
+
+fn check_date_format(name: &str, format: &str) -> Result<()> {
+    match StrftimeItems::new(format).any(|item| item == Item::Error) {
+        true => Err(anyhow!("{} is not a valid date format: '{}'", name, format)),
+        false => Ok(()),
+    }
+}
+
+/// Where the settings are read from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsLocation {
+    /// A file given with --config or in SETTINGS_ENV_VAR, which has to exist
+    Explicit(PathBuf),
+    /// The settings file in the config directory, which may be missing
+    Default(PathBuf),
+    /// There is no config directory on this system
+    Unavailable,
+}
+
+impl SettingsLocation {
+    /// The given file, the file in SETTINGS_ENV_VAR or the file in the config directory, in that order.
+    pub fn find(config: Option<&Path>) -> SettingsLocation {
+        if let Some(path) = config {
+            return SettingsLocation::Explicit(expanduser(path));
+        }
+        if let Some(path) = env::var_os(SETTINGS_ENV_VAR).filter(|path| !path.is_empty()) {
+            return SettingsLocation::Explicit(expanduser(Path::new(&path)));
+        }
+        match get_settings_folder() {
+            Some(folder) => SettingsLocation::Default(folder.join(SETTINGS_FILE_NAME)),
+            None => SettingsLocation::Unavailable,
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            SettingsLocation::Explicit(path) | SettingsLocation::Default(path) => Some(path),
+            SettingsLocation::Unavailable => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    NotFound(PathBuf),
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, serde_yaml::Error),
+    Invalid(PathBuf, anyhow::Error),
 }
 
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::NotFound(path) => write!(f, "Settings file {:?} does not exist", path),
+            SettingsError::Read(path, e) => {
+                write!(f, "Unable to read settings file {:?}: {}", path, e)
+            }
+            SettingsError::Parse(path, e) => {
+                write!(f, "Unable to parse settings file {:?}: {}", path, e)
+            }
+            SettingsError::Invalid(path, e) => {
+                write!(f, "Invalid settings in {:?}: {:#}", path, e)
+            }
+        }
+    }
+}
+
+// The messages already contain the underlying errors, so they are not returned as sources.
+impl std::error::Error for SettingsError {}
+
 impl Settings {
-    pub fn from_default_location() -> Option<Self> {
-        let file = config_dir()?.join("pundit").join("settings.yaml");
-        let handle = File::open(&file).ok()?;
-
-        let result = serde_yaml::from_reader(handle);
-
-        match result {
-            Err(e) => {
-                // TODO: proper error handling
-                eprintln!(
-                    "Error while reading settings from '{}': {}\nUsing default settings.",
-                    file.to_string_lossy(),
+    /// Read and validate the settings. A missing file at the default location results in the default settings.
+    pub fn from_location(location: &SettingsLocation) -> Result<Settings, SettingsError> {
+        let path = match location {
+            SettingsLocation::Explicit(path) => path,
+            SettingsLocation::Default(path) if path.is_file() => path,
+            _ => return Ok(Settings::default()),
+        };
+        let handle = File::open(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => SettingsError::NotFound(path.clone()),
+            _ => SettingsError::Read(path.clone(), e),
+        })?;
+        let mut settings: Settings =
+            serde_yaml::from_reader(handle).map_err(|e| SettingsError::Parse(path.clone(), e))?;
+        settings
+            .validate()
+            .map_err(|e| SettingsError::Invalid(path.clone(), e))?;
+        settings.expand_all_paths();
+        Ok(settings)
+    }
+
+    /// Read the settings. Unless `strict` is set, an unreadable or invalid settings file
+    /// only gives a warning and the default settings are used instead.
+    /// A missing explicitly given file is always an error.
+    pub fn read(location: &SettingsLocation, strict: bool) -> Result<Settings, SettingsError> {
+        match Settings::from_location(location) {
+            Err(e) if !strict && !matches!(e, SettingsError::NotFound(_)) => {
+                warn!(
+                    "Warning: {}\nUsing default settings. Pass --strict-config to make this an error.",
                     e
                 );
-                None
+                Ok(Settings::default())
             }
-            Ok(settings) => Some(settings),
+            result => result,
         }
     }
 
-    pub fn expand_all_paths(&mut self) -> Result<()> {
-        if let Some(ref path) = self.pundit_folder {
-            self.pundit_folder = Some(expanduser(path)?);
+    /// Check that all formats can be used to write and read back notes.
+    pub fn validate(&self) -> Result<()> {
+        self.note.validate()?;
+        self.journal.validate()?;
+        self.paper.validate()
+    }
+
+    pub fn get_profile(&self, name: &str) -> Result<&Profile> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| anyhow!("No profile named '{}' found in settings", name))
+    }
+
+    pub fn expand_all_paths(&mut self) {
+        let mut paths = vec![&mut self.pundit_folder, &mut self.template_folder];
+        for profile in self.profiles.values_mut() {
+            paths.extend(vec![
+                &mut profile.folder,
+                &mut profile.database,
+                &mut profile.anki_database,
+                &mut profile.pankit_database,
+                &mut profile.bibtex_file,
+            ]);
+        }
+        for path in paths {
+            *path = path.as_deref().map(expanduser);
         }
-        Ok(())
     }
 }
 
-pub fn expanduser(path: &Path) -> Result<PathBuf> {
-    let expanded = shellexpand::tilde(path.to_str().unwrap());
-    Path::new(&*expanded)
-        .canonicalize()
-        .context(format!("While reading {}", &expanded))
+fn get_settings_folder() -> Option<PathBuf> {
+    Some(config_dir()?.join("pundit"))
+}
+
+pub fn get_default_template_folder() -> Option<PathBuf> {
+    Some(get_settings_folder()?.join(TEMPLATE_FOLDER_NAME))
+}
+
+/// Replace a leading ~ by the home directory. Existing paths are canonicalized, all others
+/// (such as databases which are yet to be created) are only expanded.
+pub fn expanduser(path: &Path) -> PathBuf {
+    let expanded = PathBuf::from(&*shellexpand::tilde(path.to_str().unwrap()));
+    expanded.canonicalize().unwrap_or(expanded)
 }
@@ -0,0 +1,171 @@
+use anyhow::Result;
+use generational_arena::Index;
+use log::info;
+use serde::Serialize;
+
+use crate::args::OutputFormat;
+use crate::dir_utils::get_relative_path;
+use crate::filter_options::get_num_backlinks;
+use crate::filter_options::note_is_orphan;
+use crate::graph::get_articulation_points;
+use crate::graph::get_connected_components;
+use crate::note::Note;
+use crate::notes::Notes;
+
+#[derive(Serialize)]
+pub struct NoteInfo {
+    pub title: String,
+    /// The path of the note relative to the note folder
+    pub path: String,
+}
+
+#[derive(Serialize)]
+pub struct Hub {
+    pub note: NoteInfo,
+    pub num_backlinks: usize,
+}
+
+#[derive(Serialize)]
+pub struct Component {
+    pub size: usize,
+    /// The note with the most backlinks in the component
+    pub main_note: NoteInfo,
+}
+
+#[derive(Serialize)]
+pub struct Stats {
+    pub num_notes: usize,
+    pub num_links: usize,
+    /// The notes with the most backlinks
+    pub hubs: Vec<Hub>,
+    /// Notes without links from or to other notes
+    pub orphans: Vec<NoteInfo>,
+    /// Notes which other notes link to but which do not link to any note
+    pub dead_ends: Vec<NoteInfo>,
+    /// Notes whose removal would split their component
+    pub articulation_points: Vec<NoteInfo>,
+    /// The connected components (ignoring link directions), largest first
+    pub components: Vec<Component>,
+}
+
+impl Stats {
+    pub fn from_notes(notes: &Notes, num_hubs: usize) -> Result<Stats> {
+        let get_info = |note: &Note| -> Result<NoteInfo> {
+            Ok(NoteInfo {
+                title: note.title.clone(),
+                path: get_relative_path(&note.filename, &notes.folder)?
+                    .to_str()
+                    .unwrap()
+                    .to_owned(),
+            })
+        };
+        let get_infos = |notes: Vec<&Note>| -> Result<Vec<NoteInfo>> {
+            notes.into_iter().map(get_info).collect()
+        };
+        let mut sorted_notes: Vec<&Note> = notes.iter().collect();
+        sorted_notes.sort_by(|n1, n2| n1.title.cmp(&n2.title));
+
+        let mut hub_notes = sorted_notes.clone();
+        hub_notes.sort_by_key(|note| std::cmp::Reverse(get_num_backlinks(note)));
+        let hubs = hub_notes
+            .into_iter()
+            .filter(|note| get_num_backlinks(note) > 0)
+            .take(num_hubs)
+            .map(|note| {
+                Ok(Hub {
+                    note: get_info(note)?,
+                    num_backlinks: get_num_backlinks(note),
+                })
+            })
+            .collect::<Result<Vec<Hub>>>()?;
+
+        let articulation_points = get_articulation_points(notes);
+        let components = get_connected_components(notes)
+            .into_iter()
+            .map(|component| {
+                Ok(Component {
+                    size: component.len(),
+                    main_note: get_info(get_main_note(notes, &component))?,
+                })
+            })
+            .collect::<Result<Vec<Component>>>()?;
+
+        Ok(Stats {
+            num_notes: notes.len(),
+            num_links: notes.iter().map(|note| note.links.len()).sum(),
+            hubs,
+            orphans: get_infos(
+                sorted_notes
+                    .iter()
+                    .copied()
+                    .filter(|note| note_is_orphan(note))
+                    .collect(),
+            )?,
+            dead_ends: get_infos(
+                sorted_notes
+                    .iter()
+                    .copied()
+                    .filter(|note| note.links.is_empty() && !note.backlinks.is_empty())
+                    .collect(),
+            )?,
+            articulation_points: get_infos(
+                sorted_notes
+                    .iter()
+                    .copied()
+                    .filter(|note| {
+                        notes
+                            .find_index_by_filename(&note.filename)
+                            .map(|index| articulation_points.contains(&index))
+                            .unwrap_or(false)
+                    })
+                    .collect(),
+            )?,
+            components,
+        })
+    }
+
+    fn show_text(&self) {
+        info!("Notes: {}", self.num_notes);
+        info!("Links: {}", self.num_links);
+        info!("Most linked-to notes:");
+        for hub in self.hubs.iter() {
+            info!("\t{}\t{}", hub.num_backlinks, hub.note.title);
+        }
+        let lists = [
+            ("Orphans", &self.orphans),
+            ("Dead ends", &self.dead_ends),
+            ("Articulation points", &self.articulation_points),
+        ];
+        for (name, list) in lists.iter() {
+            info!("{} ({}):", name, list.len());
+            for note in list.iter() {
+                info!("\t{}", note.title);
+            }
+        }
+        info!("Components ({}):", self.components.len());
+        for component in self.components.iter() {
+            info!("\t{}\t{}", component.size, component.main_note.title);
+        }
+    }
+}
+
+fn get_main_note<'a>(notes: &'a Notes, component: &[Index]) -> &'a Note {
+    component
+        .iter()
+        .map(|index| &notes[*index])
+        .max_by(|n1, n2| {
+            get_num_backlinks(n1)
+                .cmp(&get_num_backlinks(n2))
+                .then_with(|| n2.title.cmp(&n1.title))
+        })
+        .unwrap()
+}
+
+pub fn show_stats(notes: &Notes, num_hubs: usize, output: &OutputFormat) -> Result<()> {
+    let stats = Stats::from_notes(notes, num_hubs)?;
+    match output {
+        OutputFormat::Text => stats.show_text(),
+        OutputFormat::Json => info!("{}", serde_json::to_string_pretty(&stats)?),
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use anyhow::Result;
+use log::info;
+use regex::Regex;
+
+use crate::backlink_edit::FileEdit;
+use crate::backlink_edit::LinkEdit;
+use crate::note::Note;
+use crate::notes::Notes;
+
+/// A mention of the title of another note in the contents of a note.
+pub struct Mention<'a> {
+    pub target: &'a Note,
+    pub line_number: usize,
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// Find all mentions of titles of other notes (ignoring case) that are not part of a link.
+/// Notes which are already linked to are left out. If titles overlap, the longer one is used.
+/// The header of the note (org keyword lines or markdown front matter) is not searched.
+pub fn get_unlinked_mentions<'a>(notes: &'a Notes, note: &Note) -> Result<Vec<Mention<'a>>> {
+    let contents = note.get_contents()?;
+    let format = note.format();
+    let title_line = format
+        .find_title_line(&contents)
+        .map(|(line_number, _)| line_number + 1);
+    let header_length = format.get_header_length(&contents);
+    let mut taken = format.get_link_ranges(&contents);
+    let mut targets: Vec<&Note> = notes
+        .index_iter()
+        .filter(|(index, target)| {
+            target.filename != note.filename
+                && !target.title.is_empty()
+                && !note.links.contains(index)
+        })
+        .map(|(_, target)| target)
+        .collect();
+    targets.sort_by(|n1, n2| {
+        n2.title
+            .len()
+            .cmp(&n1.title.len())
+            .then(n1.title.cmp(&n2.title))
+    });
+    let mut mentions = vec![];
+    for target in targets {
+        let regex = Regex::new(&format!("(?i){}", regex::escape(&target.title))).unwrap();
+        for m in regex.find_iter(&contents) {
+            let range = m.range();
+            let line_number = contents[..range.start].matches('\n').count() + 1;
+            if !is_on_word_boundaries(&contents, &range)
+                || line_number <= header_length
+                || Some(line_number) == title_line
+                || taken.iter().any(|other| overlaps(other, &range))
+            {
+                continue;
+            }
+            taken.push(range.clone());
+            mentions.push(Mention {
+                target,
+                line_number,
+                text: m.as_str().to_owned(),
+                range,
+            });
+        }
+    }
+    mentions.sort_by_key(|mention| mention.range.start);
+    Ok(mentions)
+}
+
+fn is_on_word_boundaries(contents: &str, range: &Range<usize>) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let before = contents[..range.start].chars().next_back();
+    let after = contents[range.end..].chars().next();
+    !before.map(is_word_char).unwrap_or(false) && !after.map(is_word_char).unwrap_or(false)
+}
+
+fn overlaps(r1: &Range<usize>, r2: &Range<usize>) -> bool {
+    r1.start < r2.end && r2.start < r1.end
+}
+
+/// Print the unlinked mentions of other notes or, if `apply` is set, replace the first
+/// mention of every note by a link to it.
+pub fn suggest_links(notes: &Notes, note: &Note, apply: bool) -> Result<()> {
+    let mentions = get_unlinked_mentions(notes, note)?;
+    if !apply {
+        for mention in mentions.iter() {
+            info!(
+                "{}: {} -> {}",
+                mention.line_number,
+                mention.text,
+                mention.target.get_link_from(note, &notes.settings)?
+            );
+        }
+        return Ok(());
+    }
+    let mut linked = HashSet::new();
+    let first_mentions: Vec<&Mention> = mentions
+        .iter()
+        .filter(|mention| linked.insert(&mention.target.filename))
+        .collect();
+    let mut contents = note.get_contents()?;
+    let mut links = vec![];
+    for mention in first_mentions.iter().rev() {
+        let link = mention.target.get_link_from(note, &notes.settings)?;
+        contents.replace_range(mention.range.clone(), &link);
+        links.push(LinkEdit {
+            old_link: mention.text.clone(),
+            new_link: link,
+        });
+    }
+    links.reverse();
+    let edit = FileEdit {
+        filename: note.filename.clone(),
+        contents,
+        links,
+    };
+    edit.show();
+    edit.write()
+}
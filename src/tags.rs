@@ -0,0 +1,29 @@
+use std::collections::BTreeSet;
+use std::fs;
+
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::note::Note;
+
+/// All tags used by the notes, sorted alphabetically.
+pub fn get_all_tags<'a>(notes: impl Iterator<Item = &'a Note>) -> BTreeSet<&'a str> {
+    notes
+        .flat_map(|note| note.tags.iter().map(|tag| tag.as_str()))
+        .collect()
+}
+
+/// Add and remove tags in the note file. Existing tags keep their order, new tags are appended.
+pub fn edit_tags(note: &Note, add: &[String], remove: &[String]) -> Result<()> {
+    let format = note.format();
+    let contents = note.get_contents()?;
+    let mut tags = format.get_tags(&contents);
+    for tag in add {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    tags.retain(|tag| !remove.contains(tag));
+    fs::write(&note.filename, format.set_tags(&contents, &tags))
+        .context(format!("While writing {:?}", note.filename))
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::Local;
+use chrono::NaiveDate;
+use regex::Regex;
+
+use crate::config::DEFAULT_TEMPLATE_NAME;
+use crate::config::TEMPLATE_DATE_FORMAT_STR;
+use crate::dir_utils::get_relative_path;
+use crate::note_format::NoteFormat;
+use crate::notes::Notes;
+use crate::settings::get_default_template_folder;
+
+/// The folder containing the user-defined templates for new notes.
+/// A template named `name` is stored in `name.org`, templates for subfolders in the same subfolders.
+pub struct Templates {
+    folder: Option<PathBuf>,
+}
+
+impl Templates {
+    /// Use the given template folder or, if there is none, the templates folder in the settings directory.
+    pub fn new(folder: Option<PathBuf>) -> Templates {
+        Templates {
+            folder: folder.or_else(get_default_template_folder),
+        }
+    }
+
+    /// The template with the given name or, if no name is given, the template for the target folder.
+    /// The template for a folder is named after its path relative to the note folder. Notes in the
+    /// note folder itself use the template named DEFAULT_TEMPLATE_NAME.
+    pub fn find(
+        &self,
+        notes_folder: &Path,
+        target_folder: &Path,
+        name: Option<&str>,
+    ) -> Result<Option<Template>> {
+        let filename = match name {
+            Some(name) => self.get_filename(Path::new(name)),
+            None => {
+                let relative_folder = get_relative_path(target_folder, notes_folder)?;
+                match relative_folder.as_os_str().is_empty() {
+                    true => self.get_filename(Path::new(DEFAULT_TEMPLATE_NAME)),
+                    false => self.get_filename(&relative_folder),
+                }
+            }
+        };
+        match (filename, name) {
+            (Some(filename), _) if filename.is_file() => Ok(Some(Template::from_file(&filename)?)),
+            (_, Some(name)) => Err(anyhow!("No template named '{}' found", name)),
+            (_, None) => Ok(None),
+        }
+    }
+
+    fn get_filename(&self, name: &Path) -> Option<PathBuf> {
+        let filename = self.folder.as_ref()?.join(name);
+        let extension = NoteFormat::default().extension();
+        Some(filename.with_file_name(format!("{}.{}", filename.file_name()?.to_str()?, extension)))
+    }
+}
+
+/// The values filled into the placeholders of a template.
+pub struct TemplateValues {
+    pub title: String,
+    pub date: NaiveDate,
+    pub citekey: Option<String>,
+    pub bibtex_fields: HashMap<String, String>,
+}
+
+impl TemplateValues {
+    pub fn new(title: &str, date: NaiveDate) -> TemplateValues {
+        TemplateValues {
+            title: title.to_owned(),
+            date,
+            citekey: None,
+            bibtex_fields: HashMap::new(),
+        }
+    }
+
+    pub fn today(title: &str) -> TemplateValues {
+        TemplateValues::new(title, Local::now().naive_local().date())
+    }
+}
+
+/// The contents of a new note which are written below its title. The placeholders
+/// {title}, {date}, {citekey}, {link:<title>} and {bibtex:<field>} are replaced by their values.
+/// All other text (including other braces) is kept as it is.
+pub struct Template {
+    contents: String,
+}
+
+impl Template {
+    pub fn from_file(filename: &Path) -> Result<Template> {
+        Ok(Template {
+            contents: fs::read_to_string(filename)
+                .context(format!("While reading template {:?}", filename))?,
+        })
+    }
+
+    /// Fill in the template for a new note in the folder. Links are written from the folder to the linked notes.
+    pub fn fill(&self, notes: &Notes, folder: &Path, values: &TemplateValues) -> Result<String> {
+        let placeholder_regex =
+            Regex::new(r"\{(title|date|citekey|link:[^{}\n]+|bibtex:[^{}\n]+)\}").unwrap();
+        let mut result = String::new();
+        let mut last_end = 0;
+        for captures in placeholder_regex.captures_iter(&self.contents) {
+            let placeholder = captures.get(0).unwrap();
+            result.push_str(&self.contents[last_end..placeholder.start()]);
+            result.push_str(&get_placeholder_value(notes, folder, values, &captures[1])?);
+            last_end = placeholder.end();
+        }
+        result.push_str(&self.contents[last_end..]);
+        Ok(result)
+    }
+}
+
+fn get_placeholder_value(
+    notes: &Notes,
+    folder: &Path,
+    values: &TemplateValues,
+    placeholder: &str,
+) -> Result<String> {
+    if let Some(title) = placeholder.strip_prefix("link:") {
+        let note = notes
+            .find_by_title(title)
+            .ok_or_else(|| anyhow!("No note with title '{}' found for link in template", title))?;
+        return note.get_link_from_folder(folder, NoteFormat::default(), &notes.settings);
+    }
+    if let Some(field) = placeholder.strip_prefix("bibtex:") {
+        return Ok(values
+            .bibtex_fields
+            .get(&field.to_lowercase())
+            .cloned()
+            .unwrap_or_default());
+    }
+    match placeholder {
+        "title" => Ok(values.title.clone()),
+        "date" => Ok(values.date.format(TEMPLATE_DATE_FORMAT_STR).to_string()),
+        "citekey" => values
+            .citekey
+            .clone()
+            .ok_or_else(|| anyhow!("The placeholder {{citekey}} can only be used in paper notes")),
+        _ => unreachable!(),
+    }
+}
+
+/// The text appended below the title of a new note: The default content, followed by the
+/// filled in template if there is one. Each non-empty part starts on a new line, regardless
+/// of the newlines around the default content.
+pub fn get_new_note_content(
+    notes: &Notes,
+    folder: &Path,
+    template: Option<&Template>,
+    values: &TemplateValues,
+    default_content: &str,
+) -> Result<String> {
+    let filled = match template {
+        Some(template) => template.fill(notes, folder, values)?,
+        None => String::new(),
+    };
+    Ok([default_content.trim(), filled.trim_end()]
+        .iter()
+        .filter(|part| !part.is_empty())
+        .map(|part| format!("\n{}", part))
+        .collect())
+}
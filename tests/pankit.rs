@@ -1,12 +1,15 @@
+use std::fs;
 use std::path::Path;
 
 use anyhow::Result;
+use rusqlite::Connection;
+use rusqlite::NO_PARAMS;
 
 mod setup;
 mod sqlcheck;
 
 use setup::get_pundit_executable;
-use setup::run_pundit_on_setup_with_args;
+use setup::run_pundit_on_setup_with_args_and_input;
 use setup::show_output;
 use setup::TestArg;
 use setup::TestArg::NormalArg;
@@ -76,6 +79,175 @@ fn conflicting_note_contents_no_database_pundit() {
     assert!(out.success); // The program should use the changes from the pundit note and not give an error
 }
 
+#[test]
+fn conflicting_note_contents_no_database_anki() {
+    let out = run_pankit_on_setup(
+        "conflictingNoteContentsNoDatabaseForceAnki",
+        &[NormalArg("anki")],
+    )
+    .unwrap();
+    assert!(out.success); // The program should write the contents from anki into the pundit note
+    let contents = fs::read_to_string(out.env.dir.path().join("anki_note.org")).unwrap();
+    assert_eq!(
+        contents,
+        "#+TITLE: anki note
+#+BEGIN_SRC yaml
+1:
+    deck: Spanish
+    model: Spanish
+    Spanish: la palabra en Español
+    English: the word in English
+#+END_SRC
+"
+    );
+}
+
+#[test]
+fn fail_partway_changes_nothing() {
+    let out = run_pankit_on_setup(
+        "failPartway",
+        &[
+            NormalArg("anki"),
+            NormalArg("--removed-in-pundit"),
+            NormalArg("orphan"),
+        ],
+    )
+    .unwrap();
+    assert!(!out.success); // There is no deck to move the note removed from pundit to
+    let contents = fs::read_to_string(out.env.dir.path().join("anki_note.org")).unwrap();
+    assert!(contents.contains("la palabra en Español CHANGE"));
+    let pankit_db = fs::read_to_string(out.env.dir.path().join(DEFAULT_PANKIT_FILE_NAME)).unwrap();
+    assert!(!pankit_db.contains("1:"));
+}
+
+#[test]
+fn conflicting_note_contents_no_database_interactive() {
+    let out = run_pankit_on_setup_with_input(
+        "conflictingNoteContentsNoDatabaseForcePundit",
+        &[NormalArg("interactive")],
+        "p\n",
+    )
+    .unwrap();
+    assert!(out.success);
+    let out = run_pankit_on_setup_with_input(
+        "conflictingNoteContentsNoDatabaseForceAnki",
+        &[NormalArg("interactive")],
+        "a\n",
+    )
+    .unwrap();
+    assert!(out.success);
+    let contents = fs::read_to_string(out.env.dir.path().join("anki_note.org")).unwrap();
+    assert!(contents.contains("Spanish: la palabra en Español\n"));
+}
+
+#[test]
+fn backup_collection() {
+    let out = run_pankit_on_setup("addNoteToEmptyCollection", &[NormalArg("--backup")]).unwrap();
+    assert!(out.success);
+    let backups: Vec<_> = fs::read_dir(out.env.dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| {
+            name.starts_with(DEFAULT_ANKI_SOURCE_COLLECTION_NAME) && name.ends_with(".backup")
+        })
+        .collect();
+    assert_eq!(backups.len(), 1);
+}
+
+#[test]
+fn dry_run_changes_nothing() {
+    let out = run_pankit_on_setup(
+        "conflictingNoteContentsNoDatabaseForceAnki",
+        &[
+            NormalArg("anki"),
+            NormalArg("--dry-run"),
+            NormalArg("--output"),
+            NormalArg("json"),
+        ],
+    )
+    .unwrap();
+    assert!(out.success);
+    let summary: serde_json::Value = serde_json::from_str(&out.output).unwrap();
+    assert_eq!(summary["changed_in_pundit"][0]["id"], 1);
+    assert_eq!(
+        summary["changed_in_pundit"][0]["fields"][0]["new"],
+        "la palabra en Español"
+    );
+    assert_eq!(summary["conflicts"], serde_json::json!([]));
+    let contents = fs::read_to_string(out.env.dir.path().join("anki_note.org")).unwrap();
+    assert!(contents.contains("la palabra en Español CHANGE"));
+    assert!(!out.env.dir.path().join(DEFAULT_PANKIT_FILE_NAME).exists());
+}
+
+#[test]
+fn tags_from_pundit() {
+    let out = run_pankit_on_setup(
+        "tagsFromPundit",
+        &[NormalArg("--derive-tags"), NormalArg("filetags")],
+    )
+    .unwrap();
+    assert!(out.success);
+    let connection =
+        Connection::open(out.env.dir.path().join(DEFAULT_ANKI_SOURCE_COLLECTION_NAME)).unwrap();
+    let tags: String = connection
+        .query_row("SELECT tags FROM notes WHERE id = 1", NO_PARAMS, |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(tags, " spanish vocab ");
+}
+
+#[test]
+fn tags_keep_anki_tags() {
+    let out = run_pankit_on_setup("tagsKeepAnkiTags", &[]).unwrap();
+    assert!(out.success);
+    let connection =
+        Connection::open(out.env.dir.path().join(DEFAULT_ANKI_SOURCE_COLLECTION_NAME)).unwrap();
+    let tags: String = connection
+        .query_row("SELECT tags FROM notes WHERE id = 1", NO_PARAMS, |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(tags, " leech marked vocab ");
+}
+
+#[test]
+fn removed_in_pundit_delete() {
+    let out = run_pankit_on_setup(
+        "removedInPundit",
+        &[NormalArg("--removed-in-pundit"), NormalArg("delete")],
+    )
+    .unwrap();
+    assert!(out.success);
+    let pankit_db = fs::read_to_string(out.env.dir.path().join(DEFAULT_PANKIT_FILE_NAME)).unwrap();
+    assert!(!pankit_db.contains("1:"));
+}
+
+#[test]
+fn removed_in_pundit_suspend() {
+    let out = run_pankit_on_setup(
+        "removedInPunditSuspend",
+        &[NormalArg("--removed-in-pundit"), NormalArg("suspend")],
+    )
+    .unwrap();
+    assert!(out.success);
+    // Otherwise the cards would be suspended again on every run.
+    let pankit_db = fs::read_to_string(out.env.dir.path().join(DEFAULT_PANKIT_FILE_NAME)).unwrap();
+    assert!(!pankit_db.contains("1:"));
+}
+
+#[test]
+fn deleted_in_anki_remove() {
+    let out = run_pankit_on_setup(
+        "deletedInAnki",
+        &[NormalArg("--deleted-in-anki"), NormalArg("remove")],
+    )
+    .unwrap();
+    assert!(out.success);
+    let contents = fs::read_to_string(out.env.dir.path().join("anki_note.org")).unwrap();
+    assert_eq!(contents, "#+TITLE: anki note\nSome text.\nMore text.\n");
+}
+
 #[test]
 fn add_note_default_deck_model() {
     assert!(
@@ -104,17 +276,26 @@ fn integer_in_sort_field() {
 }
 
 fn run_pankit_on_setup(setup_name: &str, args: &[TestArg]) -> Result<TestOutput> {
+    run_pankit_on_setup_with_input(setup_name, args, "")
+}
+
+fn run_pankit_on_setup_with_input(
+    setup_name: &str,
+    args: &[TestArg],
+    input: &str,
+) -> Result<TestOutput> {
     let mut new_args = vec![
         NormalArg("pankit"),
         RelativePath(&DEFAULT_ANKI_SOURCE_COLLECTION_NAME),
         RelativePath(&DEFAULT_PANKIT_FILE_NAME),
     ];
     new_args.extend_from_slice(args);
-    let out = run_pundit_on_setup_with_args(
+    let out = run_pundit_on_setup_with_args_and_input(
         get_pundit_executable(),
         Path::new(TEST_SETUPS_PATH),
         setup_name,
         &new_args,
+        input,
     )
     .unwrap();
     show_output(&out);
@@ -1,5 +1,6 @@
 pub mod setup;
 
+use std::fs;
 use std::path::Path;
 
 use setup::run_pundit_on_setup;
@@ -108,6 +109,44 @@ fn graph() {
     }
 }
 
+#[test]
+fn shortest_path() {
+    let out = run_pundit_on_setup(
+        "graph",
+        &[
+            NormalArg("path"),
+            RelativePath("linkNote3.org"),
+            RelativePath("linkNote6.org"),
+        ],
+    );
+    assert!(out.success);
+    let lines: Vec<&str> = out.output.lines().collect();
+    assert_eq!(lines, ["linkNote3", "linkNote4", "linkNote5", "linkNote6"]);
+
+    let out = run_pundit_on_setup(
+        "graph",
+        &[
+            NormalArg("path"),
+            RelativePath("linkNote6.org"),
+            RelativePath("linkNote4.org"),
+        ],
+    );
+    assert!(!out.success);
+
+    let out = run_pundit_on_setup(
+        "graph",
+        &[
+            NormalArg("path"),
+            RelativePath("linkNote6.org"),
+            RelativePath("linkNote4.org"),
+            NormalArg("--undirected"),
+        ],
+    );
+    assert!(out.success);
+    let lines: Vec<&str> = out.output.lines().collect();
+    assert_eq!(lines, ["linkNote6", "linkNote5", "linkNote4"]);
+}
+
 #[test]
 fn multi_dir_setup() {
     let out = run_pundit_on_setup(
@@ -138,6 +177,168 @@ fn filter_subdir() {
     assert!(out.output.lines().all(|line| line != "linkNote2"));
 }
 
+#[test]
+fn rename() {
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("rename"),
+            RelativePath("20200424162358-note1.org"),
+            NormalArg("renamed note"),
+        ],
+    );
+    assert!(out.success);
+    let dir = out.env.dir.path();
+    assert!(!dir.join("20200424162358-note1.org").exists());
+    let contents = fs::read_to_string(dir.join("20200424162358-renamed_note.org")).unwrap();
+    assert_eq!(contents.lines().next().unwrap(), "#+TITLE: renamed note");
+    let contents = fs::read_to_string(dir.join("20200424162439-linkNote1.org")).unwrap();
+    assert_eq!(
+        contents.lines().nth(1).unwrap(),
+        "[[file:20200424162358-renamed_note.org][renamed note]]"
+    );
+}
+
+#[test]
+fn rename_dry_run() {
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("rename"),
+            NormalArg("--dry-run"),
+            RelativePath("20200424162358-note1.org"),
+            NormalArg("renamed note"),
+        ],
+    );
+    assert!(out.success);
+    let dir = out.env.dir.path();
+    assert!(dir.join("20200424162358-note1.org").exists());
+    assert!(!dir.join("20200424162358-renamed_note.org").exists());
+    assert!(out.output.lines().any(|line| line
+        == "\t[[file:20200424162358-note1.org][note1]] -> [[file:20200424162358-renamed_note.org][renamed note]]"));
+}
+
+#[test]
+fn fix_links() {
+    let out = run_pundit_on_setup("fixLinks", &[NormalArg("fix-links")]);
+    assert!(out.success);
+    assert!(out
+        .output
+        .lines()
+        .any(|line| line.starts_with("No note found for link to missing.org")));
+    let dir = out.env.dir.path();
+    let contents = fs::read_to_string(dir.join("20200424162358-note1.org")).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(
+        lines[1],
+        "[[file:subdir/20200424162439-linkNote1.org][linkNote1]]"
+    );
+    assert_eq!(
+        lines[2],
+        "[[file:subdir/20200424162453-newName.org][newName]]"
+    );
+    assert_eq!(lines[3], "[[file:missing.org][missing]]");
+    assert_eq!(
+        lines[4],
+        "[[file:subdir/20200424162439-linkNote1.org][my first link]]"
+    );
+    let contents = fs::read_to_string(dir.join("subdir/20200424162439-linkNote1.org")).unwrap();
+    assert_eq!(
+        contents.lines().nth(1).unwrap(),
+        "[[file:../20200424162358-note1.org][note1]]"
+    );
+}
+
+#[test]
+fn fix_links_dry_run() {
+    let out = run_pundit_on_setup(
+        "fixLinks",
+        &[NormalArg("fix-links"), NormalArg("--dry-run")],
+    );
+    assert!(out.success);
+    assert!(out.output.lines().any(|line| line
+        == "\t[[file:20200424162439-linkNote1.org][linkNote1]] -> [[file:subdir/20200424162439-linkNote1.org][linkNote1]]"));
+    let dir = out.env.dir.path();
+    let contents = fs::read_to_string(dir.join("20200424162358-note1.org")).unwrap();
+    assert_eq!(
+        contents.lines().nth(1).unwrap(),
+        "[[file:20200424162439-linkNote1.org][linkNote1]]"
+    );
+}
+
+#[test]
+fn delete() {
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("delete"),
+            RelativePath("20200424162453-linkNote2.org"),
+        ],
+    );
+    assert!(out.success);
+    assert!(!out
+        .env
+        .dir
+        .path()
+        .join("20200424162453-linkNote2.org")
+        .exists());
+
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("delete"),
+            RelativePath("20200424162358-note1.org"),
+        ],
+    );
+    assert!(!out.success);
+    assert!(out.stderr.contains("Not deleting note with 2 backlinks"));
+    assert!(out.env.dir.path().join("20200424162358-note1.org").exists());
+}
+
+#[test]
+fn delete_unlink() {
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("delete"),
+            NormalArg("--backlinks"),
+            NormalArg("unlink"),
+            RelativePath("20200424162358-note1.org"),
+        ],
+    );
+    assert!(out.success);
+    let dir = out.env.dir.path();
+    assert!(!dir.join("20200424162358-note1.org").exists());
+    let contents = fs::read_to_string(dir.join("20200424162439-linkNote1.org")).unwrap();
+    assert_eq!(contents.lines().nth(1).unwrap(), "note1");
+}
+
+#[test]
+fn delete_replace() {
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("delete"),
+            NormalArg("--backlinks"),
+            NormalArg("replace"),
+            NormalArg("--replacement"),
+            RelativePath("20200424162439-linkNote1.org"),
+            RelativePath("20200424162358-note1.org"),
+        ],
+    );
+    assert!(out.success);
+    let dir = out.env.dir.path();
+    assert!(!dir.join("20200424162358-note1.org").exists());
+    let contents = fs::read_to_string(dir.join("20200424162453-linkNote2.org")).unwrap();
+    assert_eq!(
+        contents.lines().nth(1).unwrap(),
+        "[[file:20200424162439-linkNote1.org][linkNote1]]"
+    );
+    // The replacement note itself should not link to itself.
+    let contents = fs::read_to_string(dir.join("20200424162439-linkNote1.org")).unwrap();
+    assert_eq!(contents.lines().nth(1).unwrap(), "note1");
+}
+
 pub fn get_abs_path_of_note(env: TestEnv, note_filename: &str) -> String {
     env.dir
         .path()
@@ -146,3 +347,71 @@ pub fn get_abs_path_of_note(env: TestEnv, note_filename: &str) -> String {
         .expect("Converting note name to path")
         .to_owned()
 }
+
+#[test]
+fn suggest_links() {
+    let out = run_pundit_on_setup(
+        "suggestLinks",
+        &[
+            NormalArg("suggest-links"),
+            RelativePath("20200101120003-main.org"),
+        ],
+    );
+    assert!(out.success);
+    assert_eq!(
+        out.output.lines().collect::<Vec<&str>>(),
+        vec![
+            "2: Graph theory -> [[file:20200101120001-graphTheory.org][Graph theory]]",
+            "3: graph -> [[file:20200101120000-graph.org][Graph]]",
+            "4: graph -> [[file:20200101120000-graph.org][Graph]]",
+        ]
+    );
+}
+
+#[test]
+fn suggest_links_skips_header() {
+    for (filename, suggestion) in [
+        (
+            "20200101120004-tagged.org",
+            "3: graph -> [[file:20200101120000-graph.org][Graph]]",
+        ),
+        (
+            "20200101120005-front.md",
+            "6: graph -> [Graph](20200101120000-graph.org)",
+        ),
+    ] {
+        let out = run_pundit_on_setup(
+            "suggestLinks",
+            &[NormalArg("suggest-links"), RelativePath(filename)],
+        );
+        assert!(out.success);
+        assert_eq!(out.output.lines().collect::<Vec<&str>>(), vec![suggestion]);
+    }
+}
+
+#[test]
+fn suggest_links_apply() {
+    let out = run_pundit_on_setup(
+        "suggestLinks",
+        &[
+            NormalArg("suggest-links"),
+            RelativePath("20200101120003-main.org"),
+            NormalArg("--apply"),
+        ],
+    );
+    assert!(out.success);
+    let contents = fs::read_to_string(out.env.dir.path().join("20200101120003-main.org")).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(
+        lines[1],
+        "[[file:20200101120001-graphTheory.org][Graph theory]] is the study of graphs."
+    );
+    assert_eq!(
+        lines[2],
+        "A [[file:20200101120000-graph.org][Graph]] consists of nodes. See [[file:20200101120002-linked.org][Linked]]."
+    );
+    assert_eq!(
+        lines[3],
+        "The Linked note is already linked. Another graph here."
+    );
+}
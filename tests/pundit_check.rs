@@ -0,0 +1,91 @@
+pub mod setup;
+
+use setup::run_pundit_on_setup;
+use setup::TestArg::NormalArg;
+
+fn has_problem(output: &str, filename: &str, location: &str, message: &str) -> bool {
+    output
+        .lines()
+        .any(|line| line.contains(&format!("{}{}: ", filename, location)) && line.contains(message))
+}
+
+#[test]
+fn check() {
+    let out = run_pundit_on_setup("check", &[NormalArg("check")]);
+    assert!(!out.success);
+    assert_eq!(out.output.lines().count(), 7);
+    assert!(has_problem(
+        &out.output,
+        "note1.org",
+        ":4",
+        "error: Link to missing.org"
+    ));
+    assert!(has_problem(
+        &out.output,
+        "note2.org",
+        ":3",
+        "error: Note links to itself"
+    ));
+    assert!(has_problem(
+        &out.output,
+        "noTitle.org",
+        ":1",
+        "error: Invalid title"
+    ));
+    assert!(has_problem(
+        &out.output,
+        "note1.org",
+        ":1",
+        "warning: Title 'note1'"
+    ));
+    assert!(has_problem(
+        &out.output,
+        "duplicate.org",
+        ":1",
+        "warning: Title 'note1'"
+    ));
+    assert!(has_problem(
+        &out.output,
+        "orphan.org",
+        "",
+        "warning: No links"
+    ));
+    assert!(has_problem(
+        &out.output,
+        "duplicate.org",
+        "",
+        "warning: No links"
+    ));
+}
+
+#[test]
+fn check_json() {
+    let out = run_pundit_on_setup(
+        "check",
+        &[NormalArg("check"), NormalArg("--output"), NormalArg("json")],
+    );
+    assert!(!out.success);
+    let problems: serde_json::Value = serde_json::from_str(&out.output).unwrap();
+    let problems = problems.as_array().unwrap();
+    assert_eq!(problems.len(), 7);
+    assert!(problems.iter().any(|problem| {
+        problem["filename"].as_str().unwrap().ends_with("note1.org")
+            && problem["line"] == 4
+            && problem["kind"] == "broken_link"
+            && problem["severity"] == "error"
+    }));
+    assert!(problems.iter().any(|problem| {
+        problem["filename"]
+            .as_str()
+            .unwrap()
+            .ends_with("orphan.org")
+            && problem["line"].is_null()
+            && problem["kind"] == "orphan"
+    }));
+}
+
+#[test]
+fn check_valid_notes() {
+    let out = run_pundit_on_setup("3linkedNotes", &[NormalArg("check")]);
+    assert!(out.success);
+}
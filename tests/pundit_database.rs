@@ -0,0 +1,112 @@
+pub mod setup;
+
+use std::fs;
+use std::path::Path;
+
+use rusqlite::Connection;
+use rusqlite::NO_PARAMS;
+use setup::get_pundit_executable;
+use setup::run_pundit;
+use setup::setup_test;
+use setup::TestArg::NormalArg;
+use setup::TestArg::RelativePath;
+use setup::TestEnv;
+use setup::TEST_SETUPS_PATH;
+
+fn run_with_database(env: &TestEnv, database_name: &str, args: &[&str]) -> Vec<String> {
+    let mut test_args = vec![RelativePath(database_name)];
+    test_args.extend(args.iter().map(|arg| NormalArg(arg)));
+    let (success, output, stderr) = run_pundit(env, &test_args).unwrap();
+    println!("{}\n{}", output, stderr);
+    assert!(success);
+    output.lines().map(|line| line.to_owned()).collect()
+}
+
+#[test]
+fn yaml_database_picks_up_changes() {
+    check_database_picks_up_changes("pundit.yaml");
+}
+
+#[test]
+fn sqlite_database_picks_up_changes() {
+    check_database_picks_up_changes("pundit.sqlite");
+}
+
+#[test]
+fn sqlite_database_stores_full_hash() {
+    let env = setup_test(
+        get_pundit_executable(),
+        Path::new(TEST_SETUPS_PATH),
+        "3linkedNotes",
+    );
+    run_with_database(&env, "pundit.sqlite", &["list"]);
+    let connection = Connection::open(env.dir.path().join("pundit.sqlite")).unwrap();
+    let hash: String = connection
+        .query_row("SELECT hash FROM notes LIMIT 1", NO_PARAMS, |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(hash.len(), 40);
+}
+
+#[test]
+fn sqlite_database_reports_note_with_invalid_link() {
+    let env = setup_test(
+        get_pundit_executable(),
+        Path::new(TEST_SETUPS_PATH),
+        "3linkedNotes",
+    );
+    let dir = env.dir.path();
+    fs::write(dir.join("other.txt"), "Not a note").unwrap();
+    fs::write(
+        dir.join("20200424162500-newNote.org"),
+        "#+TITLE: newNote\n[[file:other.txt][other]]",
+    )
+    .unwrap();
+    let (success, _, stderr) =
+        run_pundit(&env, &[RelativePath("pundit.sqlite"), NormalArg("list")]).unwrap();
+    assert!(!success);
+    assert!(stderr.contains("20200424162500-newNote.org"));
+}
+
+fn check_database_picks_up_changes(database_name: &str) {
+    let env = setup_test(
+        get_pundit_executable(),
+        Path::new(TEST_SETUPS_PATH),
+        "3linkedNotes",
+    );
+    let dir = env.dir.path();
+    let lines = run_with_database(&env, database_name, &["list"]);
+    assert_eq!(lines.len(), 3);
+    assert!(dir.join(database_name).exists());
+
+    fs::remove_file(dir.join("20200424162453-linkNote2.org")).unwrap();
+    fs::write(
+        dir.join("20200424162439-linkNote1.org"),
+        "#+TITLE: changedTitle\n[[file:20200424162358-note1.org][note1]]",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("20200424162500-newNote.org"),
+        "#+TITLE: newNote\n[[file:20200424162358-note1.org][note1]]",
+    )
+    .unwrap();
+
+    let lines = run_with_database(&env, database_name, &["list"]);
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().any(|line| line == "note1"));
+    assert!(lines.iter().any(|line| line == "changedTitle"));
+    assert!(lines.iter().any(|line| line == "newNote"));
+
+    let backlinks = run_with_database(
+        &env,
+        database_name,
+        &[
+            "list-backlinks",
+            dir.join("20200424162358-note1.org").to_str().unwrap(),
+        ],
+    );
+    assert_eq!(backlinks.len(), 2);
+    assert!(backlinks.iter().any(|line| line == "changedTitle"));
+    assert!(backlinks.iter().any(|line| line == "newNote"));
+}
@@ -0,0 +1,82 @@
+pub mod setup;
+
+use setup::run_pundit_on_setup;
+use setup::TestArg::NormalArg;
+
+fn list_filtered(filter: &[&str]) -> Vec<String> {
+    let mut args = vec![NormalArg("list")];
+    args.extend(filter.iter().map(|arg| NormalArg(arg)));
+    let out = run_pundit_on_setup("filters", &args);
+    assert!(out.success);
+    let mut titles: Vec<String> = out.output.lines().map(|line| line.to_owned()).collect();
+    titles.sort();
+    titles
+}
+
+#[test]
+fn filter_title() {
+    assert_eq!(
+        list_filtered(&["matching", "title:\"^(a|b)\""]),
+        ["alpha", "beta"]
+    );
+}
+
+#[test]
+fn filter_folder() {
+    assert_eq!(
+        list_filtered(&["matching", "folder:sub"]),
+        ["delta", "gamma"]
+    );
+    assert_eq!(list_filtered(&["matching", "folder:sub/deeper"]), ["delta"]);
+}
+
+#[test]
+fn filter_created_between() {
+    assert_eq!(
+        list_filtered(&["matching", "after:2020-06-01 and before:2021-06-01"]),
+        ["beta", "gamma"]
+    );
+    assert_eq!(
+        list_filtered(&["matching", "after:2021-01-01"]),
+        ["delta", "gamma"]
+    );
+}
+
+#[test]
+fn filter_links() {
+    assert_eq!(list_filtered(&["matching", "orphan"]), ["delta"]);
+    assert_eq!(list_filtered(&["matching", "no-links"]), ["delta", "gamma"]);
+    assert_eq!(
+        list_filtered(&["matching", "min-backlinks:1"]),
+        ["beta", "gamma"]
+    );
+    assert_eq!(list_filtered(&["matching", "min-backlinks:2"]), ["gamma"]);
+}
+
+#[test]
+fn filter_expression() {
+    assert_eq!(
+        list_filtered(&["matching", "tag:draft or folder:sub/deeper"]),
+        ["beta", "delta"]
+    );
+    assert_eq!(
+        list_filtered(&["matching", "not folder:sub and not (orphan or tag:draft)"]),
+        ["alpha", "epsilon"]
+    );
+    assert_eq!(
+        list_filtered(&[
+            "matching",
+            "no-links and after:2021-01-01 and not title:\"^(d)\""
+        ]),
+        ["gamma"]
+    );
+    let out = run_pundit_on_setup(
+        "filters",
+        &[
+            NormalArg("list"),
+            NormalArg("matching"),
+            NormalArg("orphan and (tag:draft"),
+        ],
+    );
+    assert!(!out.success);
+}
@@ -0,0 +1,202 @@
+pub mod setup;
+
+use std::fs;
+
+use setup::run_pundit_on_setup;
+use setup::TestArg::NormalArg;
+use setup::TestArg::RelativePath;
+
+#[test]
+fn export_graph_dot() {
+    let out = run_pundit_on_setup(
+        "filters",
+        &[
+            NormalArg("export-graph"),
+            NormalArg("--note"),
+            RelativePath("epsilon.org"),
+        ],
+    );
+    assert!(out.success);
+    let lines: Vec<&str> = out.output.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "digraph notes {",
+            "    0 [label=\"alpha\", path=\"20200101120000-alpha.org\", folder=\"\", tags=\"\"];",
+            "    1 [label=\"beta\", path=\"20200601120000-beta.org\", folder=\"\", tags=\"draft\"];",
+            "    2 [label=\"epsilon\", path=\"epsilon.org\", folder=\"\", tags=\"\"];",
+            "    3 [label=\"gamma\", path=\"sub/20210101120000-gamma.org\", folder=\"sub\", tags=\"\"];",
+            "    0 -> 1;",
+            "    0 -> 3;",
+            "    1 -> 3;",
+            "    2 -> 3;",
+            "}",
+        ]
+    );
+}
+
+#[test]
+fn export_graph_json() {
+    let out = run_pundit_on_setup(
+        "filters",
+        &[
+            NormalArg("export-graph"),
+            NormalArg("--format"),
+            NormalArg("json"),
+            NormalArg("--output"),
+            RelativePath("graph.json"),
+        ],
+    );
+    assert!(out.success);
+    let contents = fs::read_to_string(out.env.dir.path().join("graph.json")).unwrap();
+    let graph: serde_json::Value = serde_json::from_str(&contents).unwrap();
+    let nodes = graph["nodes"].as_array().unwrap();
+    assert_eq!(nodes.len(), 5);
+    assert_eq!(graph["edges"].as_array().unwrap().len(), 4);
+    let delta = nodes.iter().find(|node| node["title"] == "delta").unwrap();
+    assert_eq!(delta["folder"], "sub/deeper");
+    assert_eq!(delta["path"], "sub/deeper/20210601120000-delta.org");
+    let beta = nodes.iter().find(|node| node["title"] == "beta").unwrap();
+    assert_eq!(beta["tags"], serde_json::json!(["draft"]));
+}
+
+#[test]
+fn export_graph_graphml() {
+    let out = run_pundit_on_setup(
+        "filters",
+        &[
+            NormalArg("export-graph"),
+            NormalArg("--format"),
+            NormalArg("graphml"),
+        ],
+    );
+    assert!(out.success);
+    assert!(out.output.starts_with("<?xml"));
+    assert_eq!(out.output.matches("<node id=").count(), 5);
+    assert_eq!(out.output.matches("<edge source=").count(), 4);
+    assert!(out
+        .output
+        .contains("<data key=\"folder\">sub/deeper</data>"));
+}
+
+fn list_graph(setup: &str, note: &str, options: &[&str]) -> Vec<String> {
+    let mut args = vec![NormalArg("list-graph"), RelativePath(note)];
+    args.extend(options.iter().map(|arg| NormalArg(arg)));
+    let out = run_pundit_on_setup(setup, &args);
+    assert!(out.success);
+    let mut titles: Vec<String> = out.output.lines().map(|line| line.to_owned()).collect();
+    titles.sort();
+    titles
+}
+
+#[test]
+fn list_graph_depth() {
+    assert_eq!(
+        list_graph("graph", "linkNote4.org", &["--depth", "1"]),
+        ["linkNote3", "linkNote4", "linkNote5"]
+    );
+    assert_eq!(
+        list_graph("graph", "linkNote4.org", &["--depth", "0"]),
+        ["linkNote4"]
+    );
+}
+
+#[test]
+fn list_graph_direction() {
+    assert_eq!(
+        list_graph("graph", "linkNote4.org", &["--direction", "out"]),
+        ["linkNote4", "linkNote5", "linkNote6"]
+    );
+    assert_eq!(
+        list_graph("graph", "linkNote4.org", &["--direction", "in"]),
+        ["linkNote3", "linkNote4"]
+    );
+    assert_eq!(
+        list_graph(
+            "graph",
+            "linkNote4.org",
+            &["--direction", "out", "--depth", "1"]
+        ),
+        ["linkNote4", "linkNote5"]
+    );
+}
+
+#[test]
+fn list_graph_exclude_hubs() {
+    assert_eq!(
+        list_graph("filters", "epsilon.org", &[]),
+        ["alpha", "beta", "epsilon", "gamma"]
+    );
+    assert_eq!(
+        list_graph(
+            "filters",
+            "epsilon.org",
+            &["--exclude-hubs", "--hub-min-backlinks", "3"]
+        ),
+        ["epsilon"]
+    );
+    assert_eq!(
+        list_graph(
+            "filters",
+            "20200101120000-alpha.org",
+            &["--exclude-hubs", "--hub-min-backlinks", "3"]
+        ),
+        ["alpha", "beta"]
+    );
+}
+
+#[test]
+fn stats() {
+    let out = run_pundit_on_setup("filters", &[NormalArg("stats")]);
+    assert!(out.success);
+    let lines: Vec<&str> = out.output.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "Notes: 5",
+            "Links: 4",
+            "Most linked-to notes:",
+            "\t3\tgamma",
+            "\t1\tbeta",
+            "Orphans (1):",
+            "\tdelta",
+            "Dead ends (1):",
+            "\tgamma",
+            "Articulation points (1):",
+            "\tgamma",
+            "Components (2):",
+            "\t4\tgamma",
+            "\t1\tdelta",
+        ]
+    );
+}
+
+#[test]
+fn stats_json() {
+    let out = run_pundit_on_setup(
+        "graph",
+        &[NormalArg("stats"), NormalArg("--output"), NormalArg("json")],
+    );
+    assert!(out.success);
+    let stats: serde_json::Value = serde_json::from_str(&out.output).unwrap();
+    assert_eq!(stats["num_notes"], 6);
+    assert_eq!(stats["num_links"], 5);
+    let titles = |key: &str| -> Vec<String> {
+        stats[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|note| note["title"].as_str().unwrap().to_owned())
+            .collect()
+    };
+    assert_eq!(titles("articulation_points"), ["linkNote4", "linkNote5"]);
+    assert_eq!(titles("dead_ends"), ["linkNote6"]);
+    assert!(titles("orphans").is_empty());
+    let sizes: Vec<u64> = stats["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|component| component["size"].as_u64().unwrap())
+        .collect();
+    assert_eq!(sizes, [4, 2]);
+}
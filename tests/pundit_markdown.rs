@@ -0,0 +1,65 @@
+pub mod setup;
+
+use std::fs;
+
+use setup::run_pundit_on_setup;
+use setup::TestArg::NormalArg;
+use setup::TestArg::RelativePath;
+
+#[test]
+fn read_markdown_notes() {
+    let out = run_pundit_on_setup("markdown", &[NormalArg("list")]);
+    assert!(out.success);
+    assert_eq!(out.output.lines().count(), 3);
+    assert!(out.output.lines().any(|line| line == "markdown note"));
+    assert!(out.output.lines().any(|line| line == "orgNote"));
+    assert!(out.output.lines().any(|line| line == "headingNote"));
+}
+
+#[test]
+fn markdown_backlinks() {
+    let out = run_pundit_on_setup(
+        "markdown",
+        &[
+            NormalArg("list-backlinks"),
+            RelativePath("20200424162439-orgNote.org"),
+        ],
+    );
+    assert!(out.success);
+    assert_eq!(out.output, "markdown note\n");
+}
+
+#[test]
+fn markdown_link() {
+    let out = run_pundit_on_setup(
+        "markdown",
+        &[
+            NormalArg("show-link"),
+            RelativePath("20200424162453-headingNote.md"),
+            RelativePath("20200424162439-orgNote.org"),
+        ],
+    );
+    assert!(out.success);
+    assert_eq!(out.output, "[orgNote](20200424162439-orgNote.org)\n");
+}
+
+#[test]
+fn rename_markdown_note() {
+    let out = run_pundit_on_setup(
+        "markdown",
+        &[
+            NormalArg("rename"),
+            RelativePath("20200424162358-markdownNote.md"),
+            NormalArg("renamed"),
+        ],
+    );
+    assert!(out.success);
+    let dir = out.env.dir.path();
+    let contents = fs::read_to_string(dir.join("20200424162358-renamed.md")).unwrap();
+    assert_eq!(contents.lines().nth(1).unwrap(), "title: renamed");
+    let contents = fs::read_to_string(dir.join("20200424162453-headingNote.md")).unwrap();
+    assert_eq!(
+        contents.lines().nth(2).unwrap(),
+        "[renamed](20200424162358-renamed.md)"
+    );
+}
@@ -0,0 +1,115 @@
+pub mod setup;
+
+use std::fs;
+use std::path::Path;
+
+use setup::get_pundit_executable;
+use setup::run_pundit;
+use setup::run_pundit_on_setup;
+use setup::setup_test;
+use setup::TestArg::NormalArg;
+use setup::TestArg::RelativePath;
+use setup::TEST_SETUPS_PATH;
+
+fn get_matches(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .map(|line| {
+            let (location, snippet) = line.split_once(": ").unwrap();
+            let filename = Path::new(location).file_name().unwrap().to_str().unwrap();
+            (filename.to_owned(), snippet.to_owned())
+        })
+        .collect()
+}
+
+#[test]
+fn search_words() {
+    let out = run_pundit_on_setup(
+        "search",
+        &[NormalArg("search"), NormalArg("Apples"), NormalArg("red")],
+    );
+    assert!(out.success);
+    let matches = get_matches(&out.output);
+    assert_eq!(
+        matches,
+        [
+            (
+                "20200424162358-apples.org:1".to_owned(),
+                "#+TITLE: apples".to_owned()
+            ),
+            (
+                "20200424162358-apples.org:2".to_owned(),
+                "Apples grow on trees.".to_owned()
+            ),
+            (
+                "20200424162358-apples.org:3".to_owned(),
+                "Some apples are red, others are green.".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn search_regex() {
+    let out = run_pundit_on_setup(
+        "search",
+        &[
+            NormalArg("search"),
+            NormalArg("--regex"),
+            NormalArg("gr[ea]en|yell"),
+        ],
+    );
+    assert!(out.success);
+    let matches = get_matches(&out.output);
+    assert_eq!(
+        matches,
+        [
+            (
+                "20200424162358-apples.org:3".to_owned(),
+                "Some apples are red, others are green.".to_owned()
+            ),
+            (
+                "20200424162439-pears.org:3".to_owned(),
+                "They are green or yellow.".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn search_with_index() {
+    let env = setup_test(
+        get_pundit_executable(),
+        Path::new(TEST_SETUPS_PATH),
+        "search",
+    );
+    let dir = env.dir.path();
+    let search = |word| {
+        let (success, output, _) = run_pundit(
+            &env,
+            &[
+                RelativePath("pundit.yaml"),
+                NormalArg("search"),
+                NormalArg(word),
+            ],
+        )
+        .unwrap();
+        assert!(success);
+        get_matches(&output)
+    };
+    assert_eq!(search("yellow").len(), 1);
+    assert!(dir.join("pundit.search_index.json").exists());
+    fs::write(
+        dir.join("20200424162439-pears.org"),
+        "#+TITLE: pears\nPears are brown.\n",
+    )
+    .unwrap();
+    assert_eq!(search("yellow").len(), 0);
+    assert_eq!(
+        search("brown"),
+        [(
+            "20200424162439-pears.org:2".to_owned(),
+            "Pears are brown.".to_owned()
+        )]
+    );
+}
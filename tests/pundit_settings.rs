@@ -0,0 +1,201 @@
+pub mod setup;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use regex::Regex;
+use setup::run_pundit_on_setup;
+use setup::TestArg::AbsolutePath;
+use setup::TestArg::NormalArg;
+use tempdir::TempDir;
+
+fn create_settings(contents: &str) -> (TempDir, PathBuf) {
+    let dir = TempDir::new("punditSettings").unwrap();
+    let file = dir.path().join("settings.yaml");
+    fs::write(&file, contents).unwrap();
+    (dir, file)
+}
+
+#[test]
+fn config_path() {
+    let (_dir, file) = create_settings("");
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&file),
+            NormalArg("config"),
+            NormalArg("path"),
+        ],
+    );
+    assert!(out.success);
+    assert_eq!(
+        out.output.trim(),
+        file.canonicalize().unwrap().to_str().unwrap()
+    );
+}
+
+#[test]
+fn config_show_with_profile() {
+    let (_dir, file) = create_settings(
+        "profiles:
+  work:
+    database: ~/work.sqlite
+    anki_database: collection.anki2
+",
+    );
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&file),
+            NormalArg("--profile"),
+            NormalArg("work"),
+            NormalArg("config"),
+            NormalArg("show"),
+        ],
+    );
+    assert!(out.success);
+    let home = env::var("HOME").unwrap();
+    assert!(out
+        .output
+        .contains(&format!("database: {}/work.sqlite", home)));
+    assert!(out.output.contains("profile: work"));
+    assert!(out.output.contains("anki_database: collection.anki2"));
+    assert!(out.output.contains("title_string: \"#+TITLE: \""));
+}
+
+#[test]
+fn profile_database() {
+    let database_dir = TempDir::new("punditDatabase").unwrap();
+    let database = database_dir.path().join("notes.sqlite");
+    let (_dir, file) = create_settings(&format!(
+        "profiles:\n  work:\n    database: {}\n",
+        database.to_str().unwrap()
+    ));
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&file),
+            NormalArg("--profile"),
+            NormalArg("work"),
+            NormalArg("list"),
+        ],
+    );
+    assert!(out.success);
+    assert_eq!(out.output.lines().count(), 3);
+    assert!(database.is_file());
+}
+
+#[test]
+fn unknown_profile() {
+    let (_dir, file) = create_settings("");
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&file),
+            NormalArg("--profile"),
+            NormalArg("work"),
+            NormalArg("list"),
+        ],
+    );
+    assert!(!out.success);
+}
+
+#[test]
+fn missing_config_file() {
+    let dir = TempDir::new("punditSettings").unwrap();
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&dir.path().join("settings.yaml")),
+            NormalArg("list"),
+        ],
+    );
+    assert!(!out.success);
+}
+
+#[test]
+fn invalid_settings_fall_back_to_defaults() {
+    let (_dir, file) = create_settings("note:\n  title_string: \"\"\n");
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&file),
+            NormalArg("list"),
+        ],
+    );
+    assert!(out.success);
+    assert_eq!(out.output.lines().count(), 3);
+    assert!(out.stderr.contains("title_string must not be empty"));
+}
+
+#[test]
+fn invalid_settings_strict() {
+    let (_dir, file) = create_settings("note:\n  title_string: \"\"\n");
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&file),
+            NormalArg("--strict-config"),
+            NormalArg("list"),
+        ],
+    );
+    assert!(!out.success);
+    assert_eq!(out.output, "");
+}
+
+#[test]
+fn config_validate() {
+    let (_dir, file) =
+        create_settings("journal:\n  date_format: \"%Y\"\n  title_format: \"{name}\"\n");
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&file),
+            NormalArg("config"),
+            NormalArg("validate"),
+        ],
+    );
+    assert!(!out.success);
+    let (_dir, file) = create_settings("journal:\n  date_format: \"%d.%m.%Y\"\n");
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&file),
+            NormalArg("config"),
+            NormalArg("validate"),
+        ],
+    );
+    assert!(out.success);
+}
+
+#[test]
+fn journal_title_format() {
+    let (_dir, file) = create_settings(
+        "journal:\n  title_format: \"{date} {name}\"\n  date_format: \"%Y-%m-%d\"\n  in_subfolders: false\n",
+    );
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--config"),
+            AbsolutePath(&file),
+            NormalArg("journal"),
+            NormalArg("work"),
+            NormalArg("today"),
+        ],
+    );
+    assert!(out.success);
+    let contents = fs::read_to_string(out.output.lines().next().unwrap()).unwrap();
+    assert!(Regex::new(r"^#\+TITLE: \d{4}-\d{2}-\d{2} work$")
+        .unwrap()
+        .is_match(contents.lines().next().unwrap()));
+}
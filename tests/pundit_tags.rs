@@ -0,0 +1,190 @@
+pub mod setup;
+
+use std::fs;
+use std::path::Path;
+
+use setup::get_pundit_executable;
+use setup::run_pundit;
+use setup::run_pundit_on_setup;
+use setup::setup_test;
+use setup::TestArg::NormalArg;
+use setup::TestArg::RelativePath;
+use setup::TEST_SETUPS_PATH;
+
+#[test]
+fn list_tags() {
+    let out = run_pundit_on_setup("tags", &[NormalArg("list-tags")]);
+    assert!(out.success);
+    let lines: Vec<&str> = out.output.lines().collect();
+    assert_eq!(lines, ["math", "physics", "reading", "todo"]);
+}
+
+#[test]
+fn filter_by_tags() {
+    let out = run_pundit_on_setup(
+        "tags",
+        &[
+            NormalArg("list"),
+            NormalArg("with-tags"),
+            NormalArg("math"),
+            NormalArg("reading"),
+        ],
+    );
+    assert!(out.success);
+    let lines: Vec<&str> = out.output.lines().collect();
+    assert_eq!(lines, ["markdownTags"]);
+
+    let out = run_pundit_on_setup(
+        "tags",
+        &[
+            NormalArg("list"),
+            NormalArg("without-tags"),
+            NormalArg("reading"),
+        ],
+    );
+    assert!(out.success);
+    let mut lines: Vec<&str> = out.output.lines().collect();
+    lines.sort();
+    assert_eq!(lines, ["tagged", "untagged"]);
+}
+
+#[test]
+fn edit_tags() {
+    let out = run_pundit_on_setup(
+        "tags",
+        &[
+            NormalArg("tag"),
+            RelativePath("20200424162358-tagged.org"),
+            NormalArg("--remove"),
+            NormalArg("todo"),
+            NormalArg("--add"),
+            NormalArg("new"),
+        ],
+    );
+    assert!(out.success);
+    let contents =
+        fs::read_to_string(out.env.dir.path().join("20200424162358-tagged.org")).unwrap();
+    assert_eq!(
+        contents,
+        "#+TITLE: tagged\n#+FILETAGS: :physics:math:new:\nSome text.\n"
+    );
+
+    let out = run_pundit_on_setup(
+        "tags",
+        &[
+            NormalArg("tag"),
+            RelativePath("20200424162439-untagged.org"),
+            NormalArg("--add"),
+            NormalArg("new"),
+        ],
+    );
+    assert!(out.success);
+    let contents =
+        fs::read_to_string(out.env.dir.path().join("20200424162439-untagged.org")).unwrap();
+    assert_eq!(
+        contents,
+        "#+TITLE: untagged\n#+FILETAGS: :new:\nNo tags here.\n"
+    );
+}
+
+#[test]
+fn edit_markdown_tags() {
+    let out = run_pundit_on_setup(
+        "tags",
+        &[
+            NormalArg("tag"),
+            RelativePath("20200424162510-listTags.md"),
+            NormalArg("--add"),
+            NormalArg("new"),
+        ],
+    );
+    assert!(out.success);
+    let contents =
+        fs::read_to_string(out.env.dir.path().join("20200424162510-listTags.md")).unwrap();
+    assert_eq!(contents, "---\ntags: [reading, new]\n---\n\n# listTags\n");
+
+    let out = run_pundit_on_setup(
+        "markdown",
+        &[
+            NormalArg("tag"),
+            RelativePath("20200424162453-headingNote.md"),
+            NormalArg("--add"),
+            NormalArg("new"),
+        ],
+    );
+    assert!(out.success);
+    let contents =
+        fs::read_to_string(out.env.dir.path().join("20200424162453-headingNote.md")).unwrap();
+    assert!(contents.starts_with("---\ntags: [new]\n---\n\n# headingNote\n"));
+}
+
+#[test]
+fn yaml_database_stores_tags() {
+    check_database_stores_tags("pundit.yaml");
+}
+
+#[test]
+fn sqlite_database_stores_tags() {
+    check_database_stores_tags("pundit.sqlite");
+}
+
+fn check_database_stores_tags(database_name: &str) {
+    let env = setup_test(get_pundit_executable(), Path::new(TEST_SETUPS_PATH), "tags");
+    let list_tags = || {
+        let (success, output, _) =
+            run_pundit(&env, &[RelativePath(database_name), NormalArg("list-tags")]).unwrap();
+        assert!(success);
+        output
+            .lines()
+            .map(|line| line.to_owned())
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(list_tags(), ["math", "physics", "reading", "todo"]);
+    fs::write(
+        env.dir.path().join("20200424162358-tagged.org"),
+        "#+TITLE: tagged\n#+FILETAGS: :chemistry:\n",
+    )
+    .unwrap();
+    assert_eq!(list_tags(), ["chemistry", "math", "reading"]);
+}
+
+#[test]
+fn yaml_database_filters_tags() {
+    check_database_filters_tags("pundit.yaml");
+}
+
+#[test]
+fn sqlite_database_filters_tags() {
+    check_database_filters_tags("pundit.sqlite");
+}
+
+/// Tag lines below the header of an org note are not tags of the note, whichever database is used.
+fn check_database_filters_tags(database_name: &str) {
+    let env = setup_test(get_pundit_executable(), Path::new(TEST_SETUPS_PATH), "tags");
+    fs::write(
+        env.dir.path().join("20200424162600-lateTags.org"),
+        "#+TITLE: lateTags\n#+FILETAGS: :math:\nSome text.\n#+FILETAGS: :late:\n",
+    )
+    .unwrap();
+    let list_with_tag = |tag: &str| {
+        let (success, output, _) = run_pundit(
+            &env,
+            &[
+                RelativePath(database_name),
+                NormalArg("list"),
+                NormalArg("with-tags"),
+                NormalArg(tag),
+            ],
+        )
+        .unwrap();
+        assert!(success);
+        let mut lines: Vec<String> = output.lines().map(|line| line.to_owned()).collect();
+        lines.sort();
+        lines
+    };
+    assert_eq!(
+        list_with_tag("math"),
+        ["lateTags", "markdownTags", "tagged"]
+    );
+    assert!(list_with_tag("late").is_empty());
+}
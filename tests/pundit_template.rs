@@ -0,0 +1,115 @@
+pub mod setup;
+
+use std::fs;
+use std::path::Path;
+
+use pundit::dir_utils::get_files;
+use regex::Regex;
+use setup::run_pundit_on_setup;
+use setup::TestArg::AbsolutePath;
+use setup::TestArg::NormalArg;
+use tempdir::TempDir;
+
+fn create_templates(templates: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new("punditTemplates").unwrap();
+    for (name, contents) in templates.iter() {
+        fs::write(dir.path().join(name), contents).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn new_with_default_template() {
+    let templates = create_templates(&[(
+        "default.org",
+        "Created {date}\nSee {link:note1}\n\\frac{a}{b} {unknown}\n",
+    )]);
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--template-folder"),
+            AbsolutePath(templates.path()),
+            NormalArg("new"),
+            NormalArg("newTitle"),
+        ],
+    );
+    assert!(out.success);
+    let filename = Path::new(out.output.lines().next().unwrap());
+    let contents = fs::read_to_string(filename).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines[0], "#+TITLE: newTitle");
+    assert!(Regex::new(r"^Created \d{4}-\d{2}-\d{2}$")
+        .unwrap()
+        .is_match(lines[1]));
+    assert_eq!(lines[2], "See [[file:20200424162358-note1.org][note1]]");
+    assert_eq!(lines[3], "\\frac{a}{b} {unknown}");
+    assert_eq!(lines.len(), 4);
+}
+
+#[test]
+fn new_with_named_template() {
+    let templates = create_templates(&[
+        ("default.org", "Default\n"),
+        ("meeting.org", "* Meeting: {title}\n"),
+    ]);
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--template-folder"),
+            AbsolutePath(templates.path()),
+            NormalArg("new"),
+            NormalArg("newTitle"),
+            NormalArg("--template"),
+            NormalArg("meeting"),
+        ],
+    );
+    assert!(out.success);
+    let filename = Path::new(out.output.lines().next().unwrap());
+    let contents = fs::read_to_string(filename).unwrap();
+    assert_eq!(contents, "#+TITLE: newTitle\n* Meeting: newTitle\n");
+}
+
+#[test]
+fn new_with_missing_template() {
+    let templates = create_templates(&[]);
+    let out = run_pundit_on_setup(
+        "3linkedNotes",
+        &[
+            NormalArg("--template-folder"),
+            AbsolutePath(templates.path()),
+            NormalArg("new"),
+            NormalArg("newTitle"),
+            NormalArg("--template"),
+            NormalArg("meeting"),
+        ],
+    );
+    assert!(!out.success);
+    assert_eq!(get_files(out.env.dir.path()).unwrap().len(), 3);
+}
+
+#[test]
+fn journal_with_folder_template() {
+    let templates = create_templates(&[("work.org", "* {title} on {date}\n")]);
+    let out = run_pundit_on_setup(
+        "journalCreateDirIfNonExistent",
+        &[
+            NormalArg("--template-folder"),
+            AbsolutePath(templates.path()),
+            NormalArg("journal"),
+            NormalArg("work"),
+            NormalArg("today"),
+        ],
+    );
+    assert!(out.success);
+    let filename = Path::new(out.output.lines().next().unwrap());
+    let contents = fs::read_to_string(filename).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert!(Regex::new(r"^\[\[file:\d{14}-work.org\]\[work\]\]$")
+        .unwrap()
+        .is_match(lines[1]));
+    assert!(
+        Regex::new(r"^\* work \d{4} \d{2} \d{2} on \d{4}-\d{2}-\d{2}$")
+            .unwrap()
+            .is_match(lines[2])
+    );
+}
@@ -2,6 +2,7 @@ use std::env;
 use std::ffi::OsStr;
 use std::fmt::Display;
 use std::fs;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
@@ -76,19 +77,33 @@ pub fn setup_test(executable_name: String, setups_folder: &Path, test_name: &str
 pub fn get_shell_command_output<T: Display + AsRef<OsStr>>(
     command: &str,
     args: &[T],
+) -> (bool, String, String) {
+    get_shell_command_output_with_input(command, args, "")
+}
+
+pub fn get_shell_command_output_with_input<T: Display + AsRef<OsStr>>(
+    command: &str,
+    args: &[T],
+    input: &str,
 ) -> (bool, String, String) {
     print!("Running {}", command);
     for arg in args.iter() {
         print!(" {}", arg);
     }
     println!("");
-    let child = Command::new(command)
+    let mut child = Command::new(command)
         .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
         .expect(&format!("Failed to run command: {}", command));
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .expect("Failed to write to stdin");
 
     let output = child.wait_with_output().expect("Failed to read stdout");
     let exit_code = output.status;
@@ -103,12 +118,22 @@ pub fn get_shell_command_output<T: Display + AsRef<OsStr>>(
     )
 }
 
+#[allow(dead_code)]
 pub fn run_pundit(env: &TestEnv, args: &[TestArg]) -> Result<(bool, String, String)> {
+    run_pundit_with_input(env, args, "")
+}
+
+pub fn run_pundit_with_input(
+    env: &TestEnv,
+    args: &[TestArg],
+    input: &str,
+) -> Result<(bool, String, String)> {
     let mut new_args = vec![TestArg::AbsolutePath(env.dir.path())];
     new_args.extend_from_slice(args);
-    Ok(get_shell_command_output(
+    Ok(get_shell_command_output_with_input(
         env.executable.to_str().unwrap(),
         &convert_args(&new_args, &env.dir.path())?,
+        input,
     ))
 }
 
@@ -161,9 +186,20 @@ pub fn run_pundit_on_setup_with_args(
     setups_folder: &Path,
     setup_name: &str,
     args: &[TestArg],
+) -> Result<TestOutput> {
+    run_pundit_on_setup_with_args_and_input(binary_name, setups_folder, setup_name, args, "")
+}
+
+#[allow(dead_code)]
+pub fn run_pundit_on_setup_with_args_and_input(
+    binary_name: String,
+    setups_folder: &Path,
+    setup_name: &str,
+    args: &[TestArg],
+    input: &str,
 ) -> Result<TestOutput> {
     let env = setup_test(binary_name, setups_folder, setup_name);
-    let output = run_pundit(&env, args)?;
+    let output = run_pundit_with_input(&env, args, input)?;
     convert_to_test_output(env, output)
 }
 